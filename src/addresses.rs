@@ -0,0 +1,156 @@
+use std::cmp::Reverse;
+use std::collections::HashMap;
+
+use error::Result;
+use Address;
+use Query;
+
+/// Which addresses count as the same, as in `notmuch address --deduplicate`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Deduplicate {
+    /// Every occurrence is listed.
+    No,
+    /// Addresses with the same name and address are listed once. Like in
+    /// notmuch, the address is compared case-sensitively.
+    Mailbox,
+    /// Addresses are listed once, with the name most often used with them.
+    /// The address is compared case-insensitively.
+    Address,
+}
+
+/// The order of the addresses returned by `Query::addresses`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AddressOrder {
+    /// In the order they first appear in the messages, which follow the sort
+    /// order of the query.
+    Query,
+    /// Most often seen first, then as for `Query`.
+    Count,
+    /// Alphabetically by address, then by name.
+    Address,
+}
+
+/// What `Query::addresses` collects.
+#[derive(Clone, Copy, Debug)]
+pub struct AddressOptions {
+    /// Collect the `From` addresses.
+    pub sender: bool,
+    /// Collect the `To`, `Cc` and `Bcc` addresses.
+    pub recipients: bool,
+    pub deduplicate: Deduplicate,
+    pub order: AddressOrder,
+}
+
+impl Default for AddressOptions {
+    fn default() -> Self {
+        AddressOptions {
+            sender: true,
+            recipients: false,
+            deduplicate: Deduplicate::Mailbox,
+            order: AddressOrder::Query,
+        }
+    }
+}
+
+/// An address found by `Query::addresses`, with the number of times it was
+/// seen. The count is always 1 without deduplication.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AddressCount {
+    pub address: Address,
+    pub count: usize,
+}
+
+struct Entry {
+    count: usize,
+    /// The names used with the address, with how often each was seen, in
+    /// the order they were first seen.
+    names: Vec<(Option<String>, usize)>,
+    address: String,
+}
+
+impl Entry {
+    fn add_name(&mut self, name: &Option<String>) {
+        self.count += 1;
+        match self.names.iter_mut().find(|(n, _)| n == name) {
+            Some((_, count)) => *count += 1,
+            None => self.names.push((name.clone(), 1)),
+        }
+    }
+
+    /// The name seen most often, the first one seen on a tie.
+    fn name(&self) -> Option<&str> {
+        let mut best: Option<&(Option<String>, usize)> = None;
+        for name in &self.names {
+            if best.map_or(true, |(_, count)| name.1 > *count) {
+                best = Some(name);
+            }
+        }
+        best.and_then(|(name, _)| name.as_deref())
+    }
+}
+
+impl Query {
+    /// Collect the addresses of the messages matching the query, like
+    /// `notmuch address` does.
+    pub fn addresses(&self, options: AddressOptions) -> Result<Vec<AddressCount>> {
+        let mut entries: Vec<Entry> = vec![];
+        let mut index: HashMap<(Option<String>, String), usize> = HashMap::new();
+
+        for message in self.search_messages()? {
+            let mut addresses = vec![];
+            if options.sender {
+                addresses.extend(message.from()?);
+            }
+            if options.recipients {
+                addresses.extend(message.to()?);
+                addresses.extend(message.cc()?);
+                addresses.extend(message.bcc()?);
+            }
+
+            for address in addresses {
+                let key = match options.deduplicate {
+                    Deduplicate::No => None,
+                    Deduplicate::Mailbox => Some((address.name.clone(), address.address.clone())),
+                    Deduplicate::Address => Some((None, address.address.to_lowercase())),
+                };
+
+                let existing = key.as_ref().and_then(|key| index.get(key)).cloned();
+                let position = match existing {
+                    Some(position) => position,
+                    None => {
+                        if let Some(key) = key {
+                            index.insert(key, entries.len());
+                        }
+                        entries.push(Entry {
+                            count: 0,
+                            names: vec![],
+                            address: address.address.clone(),
+                        });
+                        entries.len() - 1
+                    }
+                };
+                entries[position].add_name(&address.name);
+            }
+        }
+
+        let mut addresses: Vec<AddressCount> = entries
+            .iter()
+            .map(|entry| AddressCount {
+                address: Address::new(entry.name(), &entry.address),
+                count: entry.count,
+            })
+            .collect();
+
+        // The sorts are stable, so ties stay in query order.
+        match options.order {
+            AddressOrder::Query => {}
+            AddressOrder::Count => addresses.sort_by_key(|a| Reverse(a.count)),
+            AddressOrder::Address => addresses.sort_by(|a, b| {
+                (a.address.address.to_lowercase(), &a.address.name)
+                    .cmp(&(b.address.address.to_lowercase(), &b.address.name))
+            }),
+        }
+
+        Ok(addresses)
+    }
+}
@@ -0,0 +1,84 @@
+use std::fmt;
+use std::io::{self, Write};
+use std::sync::Arc;
+
+use error::Result;
+use Message;
+use MimePart;
+
+/// A part of a message that is meant to be saved rather than displayed.
+///
+/// Attachments share the parsed message they were found in.
+#[derive(Clone)]
+pub struct Attachment {
+    root: Arc<MimePart>,
+    id: usize,
+}
+
+impl fmt::Debug for Attachment {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Attachment")
+            .field("id", &self.id)
+            .field("filename", &self.filename())
+            .field("content_type", &self.content_type())
+            .finish()
+    }
+}
+
+impl Attachment {
+    /// The part id, as used by `notmuch show --part`.
+    pub fn id(&self) -> usize {
+        self.id
+    }
+
+    pub fn filename(&self) -> Option<&str> {
+        self.part().filename.as_deref()
+    }
+
+    pub fn content_type(&self) -> &str {
+        &self.part().content_type
+    }
+
+    /// The size of the decoded payload in bytes. The payload is decoded to
+    /// count it, but not kept.
+    pub fn size(&self) -> usize {
+        self.part().write_body(&mut io::sink()).unwrap_or(0)
+    }
+
+    pub fn part(&self) -> &MimePart {
+        self.root
+            .find(self.id)
+            .expect("attachment part missing from its message")
+    }
+
+    /// Write the decoded payload to `writer` as it is decoded, returning
+    /// the number of bytes written.
+    pub fn write_to<W: Write>(&self, writer: &mut W) -> Result<usize> {
+        Ok(self.part().write_body(writer)?)
+    }
+}
+
+impl MimePart {
+    /// The attachments in this part and all parts below it, including those
+    /// of attached messages, in part id order.
+    pub fn into_attachments(self) -> Vec<Attachment> {
+        let root = Arc::new(self);
+        let ids: Vec<usize> = root
+            .walk()
+            .into_iter()
+            .filter(|part| part.is_attachment())
+            .map(|part| part.id)
+            .collect();
+
+        ids.into_iter()
+            .map(|id| Attachment { root: root.clone(), id })
+            .collect()
+    }
+}
+
+impl Message {
+    /// The attachments of the message, read from its first file.
+    pub fn attachments(&self) -> Result<Vec<Attachment>> {
+        Ok(self.parts()?.into_attachments())
+    }
+}
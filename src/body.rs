@@ -0,0 +1,263 @@
+use error::Result;
+use Message;
+use MimePart;
+
+/// How `Message::body_text` cleans up the text.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct BodyOptions {
+    /// Drop quoted lines, and the attribution line introducing them.
+    pub strip_quotes: bool,
+    /// Drop everything after the `-- ` signature separator.
+    pub strip_signature: bool,
+}
+
+/// The first displayable part of type `content_type`, skipping attachments
+/// and attached messages. The text of a forwarded message is not the text
+/// of the message forwarding it.
+fn find_inline<'a>(part: &'a MimePart, content_type: &str) -> Option<&'a MimePart> {
+    if part.is_attachment() {
+        return None;
+    }
+    if part.content_type == content_type {
+        return Some(part);
+    }
+    part.children
+        .iter()
+        .filter(|child| child.content_type != "message/rfc822")
+        .filter_map(|child| find_inline(child, content_type))
+        .next()
+}
+
+fn decode_entity(entity: &str) -> Option<char> {
+    let named = match entity {
+        "amp" => '&',
+        "lt" => '<',
+        "gt" => '>',
+        "quot" => '"',
+        "apos" => '\'',
+        "nbsp" => '\u{a0}',
+        _ => {
+            let code = if let Some(hex) = entity.strip_prefix("#x").or_else(|| entity.strip_prefix("#X")) {
+                u32::from_str_radix(hex, 16).ok()?
+            } else if let Some(decimal) = entity.strip_prefix('#') {
+                decimal.parse().ok()?
+            } else {
+                return None;
+            };
+            return std::char::from_u32(code);
+        }
+    };
+    Some(named)
+}
+
+/// Accumulates the text of an HTML document, prefixing lines within
+/// `<blockquote>` with `> ` so that quotes can be stripped like in plain
+/// text.
+struct TextBuilder {
+    out: String,
+    quote_depth: usize,
+    at_line_start: bool,
+    pending_space: bool,
+}
+
+impl TextBuilder {
+    fn push_text(&mut self, text: &str) {
+        for c in text.chars() {
+            // Non-breaking spaces are kept, as plain spaces.
+            if c.is_whitespace() && c != '\u{a0}' {
+                self.pending_space = !self.at_line_start;
+                continue;
+            }
+            if self.at_line_start {
+                for _ in 0..self.quote_depth {
+                    self.out.push_str("> ");
+                }
+                self.at_line_start = false;
+            } else if self.pending_space {
+                self.out.push(' ');
+            }
+            self.pending_space = false;
+            self.out.push(if c == '\u{a0}' { ' ' } else { c });
+        }
+    }
+
+    fn newline(&mut self) {
+        if self.at_line_start && self.quote_depth > 0 {
+            for _ in 0..self.quote_depth {
+                self.out.push('>');
+            }
+        }
+        self.out.push('\n');
+        self.at_line_start = true;
+        self.pending_space = false;
+    }
+
+    /// End the current line, if any, for block elements.
+    fn break_line(&mut self) {
+        if !self.at_line_start {
+            self.newline();
+        }
+    }
+}
+
+/// Convert HTML to readable text. This is no HTML parser: it drops tags,
+/// scripts and styles, breaks lines at block elements and decodes character
+/// references, which is enough for the HTML found in mail.
+fn html_to_text(html: &str) -> String {
+    let mut text = TextBuilder {
+        out: String::new(),
+        quote_depth: 0,
+        at_line_start: true,
+        pending_space: false,
+    };
+
+    let mut rest = html;
+    while !rest.is_empty() {
+        if rest.starts_with("<!--") {
+            rest = rest.find("-->").map_or("", |end| &rest[end + 3..]);
+            continue;
+        }
+
+        if rest.starts_with('<') {
+            let end = match rest.find('>') {
+                Some(end) => end,
+                None => break,
+            };
+            let tag = &rest[1..end];
+            rest = &rest[end + 1..];
+
+            let closing = tag.starts_with('/');
+            let name = tag
+                .trim_start_matches('/')
+                .chars()
+                .take_while(|c| c.is_ascii_alphanumeric())
+                .collect::<String>()
+                .to_ascii_lowercase();
+
+            match name.as_str() {
+                "script" | "style" | "head" | "title" if !closing => {
+                    let close = format!("</{}", name);
+                    rest = rest
+                        .to_ascii_lowercase()
+                        .find(&close)
+                        .map_or("", |pos| &rest[pos..]);
+                }
+                "br" => text.newline(),
+                "blockquote" => {
+                    text.break_line();
+                    if closing {
+                        text.quote_depth = text.quote_depth.saturating_sub(1);
+                    } else {
+                        text.quote_depth += 1;
+                    }
+                }
+                "li" if !closing => {
+                    text.break_line();
+                    text.push_text("* ");
+                }
+                "p" | "div" | "tr" | "ul" | "ol" | "table" | "pre" | "hr" | "h1" | "h2" | "h3" | "h4" | "h5"
+                | "h6" | "li" => text.break_line(),
+                "td" | "th" if closing => text.push_text(" "),
+                _ => {}
+            }
+            continue;
+        }
+
+        let end = rest.find('<').unwrap_or(rest.len());
+        let mut chunk = &rest[..end];
+        rest = &rest[end..];
+
+        while let Some(amp) = chunk.find('&') {
+            text.push_text(&chunk[..amp]);
+            let after = &chunk[amp + 1..];
+            let decoded = after
+                .find(';')
+                .filter(|&semi| semi <= 10)
+                .and_then(|semi| decode_entity(&after[..semi]).map(|c| (c, semi)));
+            match decoded {
+                Some((c, semi)) => {
+                    text.push_text(&c.to_string());
+                    chunk = &after[semi + 1..];
+                }
+                None => {
+                    text.push_text("&");
+                    chunk = after;
+                }
+            }
+        }
+        text.push_text(chunk);
+    }
+
+    text.out
+}
+
+fn is_quote(line: &str) -> bool {
+    line.trim_start().starts_with('>')
+}
+
+/// Whether `line` introduces a quote, like "On Monday, John wrote:".
+fn is_attribution(line: &str) -> bool {
+    let line = line.trim_end();
+    line.ends_with("wrote:") || line.ends_with("writes:")
+}
+
+/// Apply `options` to plain text, normalizing line breaks.
+fn clean_text(text: &str, options: &BodyOptions) -> String {
+    let mut lines: Vec<&str> = text.lines().map(|line| line.trim_end_matches('\r')).collect();
+
+    if options.strip_signature {
+        if let Some(pos) = lines.iter().rposition(|line| *line == "-- ") {
+            lines.truncate(pos);
+        }
+    }
+
+    if options.strip_quotes {
+        let mut kept: Vec<&str> = Vec::with_capacity(lines.len());
+        for (i, line) in lines.iter().enumerate() {
+            if is_quote(line) {
+                continue;
+            }
+            let next = lines[i + 1..].iter().find(|l| !l.trim().is_empty());
+            if is_attribution(line) && next.is_some_and(|l| is_quote(l)) {
+                continue;
+            }
+            // Don't leave runs of blank lines where quotes were.
+            if line.trim().is_empty() && kept.last().map_or(true, |l| l.trim().is_empty()) {
+                continue;
+            }
+            kept.push(line);
+        }
+        lines = kept;
+    }
+
+    while lines.last().is_some_and(|line| line.trim().is_empty()) {
+        lines.pop();
+    }
+
+    let mut out = lines.join("\n");
+    if !out.is_empty() {
+        out.push('\n');
+    }
+    out
+}
+
+impl MimePart {
+    /// The readable text below this part: the first inline `text/plain`
+    /// part, or else the first inline `text/html` part converted to text.
+    pub fn body_text(&self, options: &BodyOptions) -> Option<String> {
+        let text = match find_inline(self, "text/plain") {
+            Some(part) => part.text()?,
+            None => html_to_text(&find_inline(self, "text/html")?.text()?),
+        };
+
+        Some(clean_text(&text, options))
+    }
+}
+
+impl Message {
+    /// The readable text of the message, see `MimePart::body_text`. `None`
+    /// if the message has no text part.
+    pub fn body_text(&self, options: BodyOptions) -> Result<Option<String>> {
+        Ok(self.parts()?.body_text(&options))
+    }
+}
@@ -0,0 +1,46 @@
+use error::{Error, Result};
+use ffi::{Exclude, Status};
+use Database;
+use Messages;
+use Query;
+use QueryExpr;
+use Revision;
+
+/// Outcome of `Database::changes_since`.
+#[derive(Debug)]
+pub struct Changes {
+    /// The messages whose tags or properties changed, including excluded
+    /// ones.
+    pub messages: Messages,
+    /// The revision the changes go up to, to pass to the next call.
+    pub revision: Revision,
+}
+
+impl Database {
+    /// The messages that were modified after `since`, a revision returned
+    /// earlier by `revision()`.
+    ///
+    /// Fails if `since` belongs to another database, e.g. because the
+    /// database was recreated since, as revisions are meaningless then.
+    pub fn changes_since(&self, since: &Revision) -> Result<Changes> {
+        let revision = self.revision();
+        if revision.uuid != since.uuid {
+            return Err(Error::NotmuchVerboseError(
+                Status::IllegalArgument,
+                format!(
+                    "database uuid {} doesn't match revision uuid {}",
+                    revision.uuid, since.uuid
+                ),
+            ));
+        }
+
+        let expr = QueryExpr::lastmod(Some(since.revision + 1), Some(revision.revision));
+        let query = Query::from_expr(self, &expr)?;
+        query.set_omit_excluded(Exclude::False);
+
+        Ok(Changes {
+            messages: query.search_messages()?,
+            revision,
+        })
+    }
+}
@@ -12,6 +12,8 @@ use config_pairs::ConfigPairs;
 use error::{Error, Result};
 use ffi;
 use ffi::ConfigKey;
+#[cfg(feature = "v0_34")]
+use ffi::QuerySyntax;
 use ffi::Status;
 use utils::ToStr;
 use ConfigList;
@@ -305,6 +307,30 @@ impl Database {
         Ok(ConfigList::from_ptr(cfgs, self.clone()))
     }
 
+    /// The value of an arbitrary configuration key stored in the database.
+    /// Keys that were never set have an empty value.
+    pub fn config_string(&self, key: &str) -> Result<String> {
+        let key_str = CString::new(key).unwrap();
+
+        let mut value = ptr::null_mut();
+        unsafe { ffi::notmuch_database_get_config(self.ptr.0, key_str.as_ptr(), &mut value) }
+            .as_result()?;
+
+        let result = (value as *const libc::c_char).to_string_lossy().to_string();
+        unsafe { libc::free(value as *mut libc::c_void) };
+        Ok(result)
+    }
+
+    /// Store an arbitrary configuration key in the database. Setting an empty
+    /// value unsets the key.
+    pub fn config_set_string(&self, key: &str, val: &str) -> Result<()> {
+        let key_str = CString::new(key).unwrap();
+        let val_str = CString::new(val).unwrap();
+
+        unsafe { ffi::notmuch_database_set_config(self.ptr.0, key_str.as_ptr(), val_str.as_ptr()) }
+            .as_result()
+    }
+
     #[cfg(feature = "v0_32")]
     pub fn config(&self, key: ConfigKey) -> Option<String> {
         let val_str = unsafe { ffi::notmuch_config_get(self.ptr.0, key.into()) };
@@ -388,6 +414,24 @@ impl Database {
         Ok(Query::from_ptr(query, self.clone()))
     }
 
+    #[cfg(feature = "v0_34")]
+    pub fn create_query_with_syntax(&self, query_string: &str, syntax: QuerySyntax) -> Result<Query> {
+        let query_str = CString::new(query_string).unwrap();
+
+        let mut query = ptr::null_mut();
+        unsafe {
+            ffi::notmuch_query_create_with_syntax(
+                self.ptr.0,
+                query_str.as_ptr(),
+                syntax.into(),
+                &mut query,
+            )
+        }
+        .as_result()?;
+
+        Ok(Query::from_ptr(query, self.clone()))
+    }
+
     pub fn all_tags(&self) -> Result<Tags> {
         let tags = unsafe { ffi::notmuch_database_get_all_tags(self.ptr.0) };
 
@@ -454,19 +498,33 @@ impl Database {
     where
         P: AsRef<Path>,
     {
-        let opts = indexopts.map_or(ptr::null_mut(), |opt| opt.ptr.0);
+        self.index_file_with_status(path, indexopts).map(|(msg, _)| msg)
+    }
+
+    /// Like `index_file`, but also tells whether the file was added to a
+    /// message that already existed (`Status::DuplicateMessageID`).
+    pub(crate) fn index_file_with_status<P>(
+        &self,
+        path: P,
+        indexopts: Option<IndexOpts>,
+    ) -> Result<(Message, bool)>
+    where
+        P: AsRef<Path>,
+    {
+        let opts = indexopts.as_ref().map_or(ptr::null_mut(), |opt| opt.ptr.0);
 
         match path.as_ref().to_str() {
             Some(path_str) => {
                 let msg_path = CString::new(path_str).unwrap();
 
                 let mut msg = ptr::null_mut();
-                unsafe {
+                let status = unsafe {
                     ffi::notmuch_database_index_file(self.ptr.0, msg_path.as_ptr(), opts, &mut msg)
-                }
-                .as_result()?;
+                };
+                status.as_result()?;
 
-                Ok(Message::from_ptr(msg, self.clone()))
+                let duplicate = status == ffi::notmuch_status_t::NOTMUCH_STATUS_DUPLICATE_MESSAGE_ID;
+                Ok((Message::from_ptr(msg, self.clone()), duplicate))
             }
             None => Err(Error::NotmuchError(Status::FileError)),
         }
@@ -1,25 +1,33 @@
+use libc;
+#[cfg(feature = "v0_21")]
+use std::mem;
 use std::ops::Drop;
 use std::rc::Rc;
 
+#[cfg(feature = "v0_21")]
+use error::Error;
+use error::Result;
 use ffi;
+#[cfg(feature = "v0_21")]
+use ffi::Status;
 use Database;
 use Filenames;
 
 #[derive(Debug)]
 pub(crate) struct DirectoryPtr(*mut ffi::notmuch_directory_t);
 
+impl Drop for DirectoryPtr {
+    fn drop(&mut self) {
+        unsafe { ffi::notmuch_directory_destroy(self.0) };
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct Directory {
     ptr: Rc<DirectoryPtr>,
     owner: Database,
 }
 
-impl Drop for Directory {
-    fn drop(&mut self) {
-        unsafe { ffi::notmuch_directory_destroy(self.ptr.0) };
-    }
-}
-
 impl Directory {
     pub(crate) fn from_ptr(
         ptr: *mut ffi::notmuch_directory_t,
@@ -31,10 +39,58 @@ impl Directory {
         }
     }
 
-    fn child_directories(&self) -> Filenames {
+    /// Get the mtime of this directory, as previously stored with
+    /// `set_mtime`.
+    ///
+    /// Returns 0 if no mtime has previously been stored for this directory.
+    pub fn mtime(&self) -> i64 {
+        unsafe { ffi::notmuch_directory_get_mtime(self.ptr.0) as i64 }
+    }
+
+    /// Store an mtime within the database for this directory.
+    ///
+    /// Read the mtime from the filesystem, index all mail files in the
+    /// directory and only then store the mtime, so that a later scan can
+    /// skip directories that have not changed since.
+    pub fn set_mtime(&self, mtime: i64) -> Result<()> {
+        unsafe { ffi::notmuch_directory_set_mtime(self.ptr.0, mtime as libc::time_t) }.as_result()
+    }
+
+    /// Get a `Filenames` iterator listing the basenames of all messages in
+    /// the database within this directory.
+    pub fn child_files(&self) -> Filenames {
+        Filenames::from_ptr(
+            unsafe { ffi::notmuch_directory_get_child_files(self.ptr.0) },
+            self.clone(),
+        )
+    }
+
+    /// Get a `Filenames` iterator listing the basenames of all
+    /// sub-directories in the database within this directory.
+    pub fn child_directories(&self) -> Filenames {
         Filenames::from_ptr(
             unsafe { ffi::notmuch_directory_get_child_directories(self.ptr.0) },
             self.clone(),
         )
     }
+
+    /// Delete the directory document from the database.
+    ///
+    /// Assumes any child directories and files have been removed by the
+    /// caller. libnotmuch destroys the underlying object, so this fails with
+    /// `Status::UnsupportedOperation` while clones of this directory, or
+    /// `Filenames` iterators derived from it, are still alive.
+    #[cfg(feature = "v0_21")]
+    pub fn delete(self) -> Result<()> {
+        let ptr = match Rc::try_unwrap(self.ptr) {
+            Ok(ptr) => ptr,
+            Err(_) => return Err(Error::NotmuchError(Status::UnsupportedOperation)),
+        };
+
+        let status = unsafe { ffi::notmuch_directory_delete(ptr.0) };
+        // notmuch_directory_delete already destroyed the object
+        mem::forget(ptr);
+
+        status.as_result()
+    }
 }
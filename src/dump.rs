@@ -0,0 +1,199 @@
+use std::io::{BufRead, Write};
+
+use error::{Error, Result};
+use ffi::{Exclude, Sort, Status};
+use tag_ops::at_line;
+use utils::{hex_decode, hex_encode};
+use Database;
+use FrozenMessage;
+use TagOps;
+
+/// What to include in a dump. Both tags and properties by default.
+#[derive(Clone, Copy, Debug)]
+pub struct DumpOptions {
+    pub tags: bool,
+    pub properties: bool,
+}
+
+impl Default for DumpOptions {
+    fn default() -> Self {
+        DumpOptions {
+            tags: true,
+            properties: true,
+        }
+    }
+}
+
+/// How to restore a dump.
+///
+/// By default the tags and properties of every message mentioned in the
+/// dump are replaced by the dumped ones. With `accumulate`, they are only
+/// added to the existing ones.
+#[derive(Clone, Copy, Debug)]
+pub struct RestoreOptions {
+    pub accumulate: bool,
+    pub tags: bool,
+    pub properties: bool,
+}
+
+impl Default for RestoreOptions {
+    fn default() -> Self {
+        RestoreOptions {
+            accumulate: false,
+            tags: true,
+            properties: true,
+        }
+    }
+}
+
+fn syntax_error(line: usize, reason: &str) -> Error {
+    Error::NotmuchVerboseError(Status::IllegalArgument, format!("line {}: {}", line, reason))
+}
+
+fn decode(line: usize, s: &str) -> Result<String> {
+    hex_decode(s).ok_or_else(|| syntax_error(line, "invalid hex encoding"))
+}
+
+/// Parse a `key=value` property, both hex encoded.
+fn parse_property(line: usize, s: &str) -> Result<(String, String)> {
+    let mut parts = s.splitn(2, '=');
+    match (parts.next(), parts.next()) {
+        (Some(key), Some(value)) if !key.is_empty() => {
+            Ok((decode(line, key)?, decode(line, value)?))
+        }
+        _ => Err(syntax_error(line, "malformed property")),
+    }
+}
+
+/// Parse the (already decoded) `id:<msgid>` query of a tag line.
+fn parse_id_query(line: usize, query: &str) -> Result<String> {
+    let id = match query.get(..3) {
+        Some("id:") => &query[3..],
+        _ => return Err(syntax_error(line, "only id: queries can be restored")),
+    };
+
+    // Be generous and accept quoted terms too, as produced by
+    // `notmuch search --output=messages`.
+    if id.len() > 1 && id.starts_with('"') && id.ends_with('"') {
+        Ok(id[1..id.len() - 1].replace("\"\"", "\""))
+    } else {
+        Ok(id.to_string())
+    }
+}
+
+impl Database {
+    /// Write the tags and properties of all messages matching `query` to
+    /// `writer`, in the batch-tag format of `notmuch dump`.
+    ///
+    /// An empty query dumps all messages.
+    pub fn dump<W>(&self, query: &str, mut writer: W, options: DumpOptions) -> Result<()>
+    where
+        W: Write,
+    {
+        let query = self.create_query(if query.is_empty() { "*" } else { query })?;
+        query.set_sort(Sort::MessageID);
+        query.set_omit_excluded(Exclude::False);
+
+        let mut include = vec![];
+        if options.properties {
+            include.push("properties");
+        }
+        if options.tags {
+            include.push("tags");
+        }
+        writeln!(writer, "#notmuch-dump batch-tag:3 {}", include.join(","))?;
+
+        for message in query.search_messages()? {
+            let encoded_id = hex_encode(&message.id());
+
+            if options.properties {
+                let properties: Vec<String> = message
+                    .properties("", false)
+                    .map(|(k, v)| format!("{}={}", hex_encode(&k), hex_encode(&v)))
+                    .collect();
+                if !properties.is_empty() {
+                    writeln!(writer, "#= {} {}", encoded_id, properties.join(" "))?;
+                }
+            }
+
+            if options.tags {
+                let tags: Vec<String> = message
+                    .tags()
+                    .map(|t| format!("+{}", hex_encode(&t)))
+                    .collect();
+                writeln!(writer, "{} -- id:{}", tags.join(" "), encoded_id)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Restore tags and properties from a dump in the batch-tag format, as
+    /// written by `dump` or `notmuch dump`.
+    ///
+    /// Messages that are not in the database are skipped.
+    pub fn restore<R>(&self, reader: R, options: RestoreOptions) -> Result<()>
+    where
+        R: BufRead,
+    {
+        for (n, line) in reader.lines().enumerate() {
+            let line = line?;
+            let n = n + 1;
+            let line = line.trim();
+
+            if let Some(properties) = line.strip_prefix("#=") {
+                if options.properties {
+                    self.restore_properties(n, properties, options)?;
+                }
+            } else if line.is_empty() || line.starts_with('#') {
+                continue;
+            } else if options.tags {
+                self.restore_tags(n, line, options)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn restore_properties(&self, n: usize, line: &str, options: RestoreOptions) -> Result<()> {
+        let mut words = line.split_whitespace();
+        let id = match words.next() {
+            Some(id) => decode(n, id)?,
+            None => return Err(syntax_error(n, "missing message id")),
+        };
+        let properties = words
+            .map(|p| parse_property(n, p))
+            .collect::<Result<Vec<_>>>()?;
+
+        let message = match self.find_message(&id)? {
+            Some(message) => message,
+            None => return Ok(()),
+        };
+
+        let _frozen = FrozenMessage::new(&message)?;
+        if !options.accumulate {
+            message.remove_all_properties(None)?;
+        }
+        for (key, value) in properties {
+            message.add_property(&key, &value)?;
+        }
+
+        Ok(())
+    }
+
+    fn restore_tags(&self, n: usize, line: &str, options: RestoreOptions) -> Result<()> {
+        let (mut ops, query) = match TagOps::parse_dump_line(line).map_err(|e| at_line(n, e))? {
+            Some(parsed) => parsed,
+            None => return Ok(()),
+        };
+
+        let id = parse_id_query(n, &query)?;
+        let message = match self.find_message(&id)? {
+            Some(message) => message,
+            None => return Ok(()),
+        };
+
+        ops.remove_all = !options.accumulate;
+        message.apply(&ops)
+    }
+}
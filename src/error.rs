@@ -1,5 +1,5 @@
 use std;
-use std::{error, fmt, io, result};
+use std::{error, fmt, io, process, result};
 
 use ffi;
 
@@ -10,6 +10,11 @@ pub enum Error {
     IoError(io::Error),
     NotmuchError(ffi::Status),
     NotmuchVerboseError(ffi::Status, String),
+    /// A hook script, named by the first field, exited unsuccessfully.
+    HookError(String, process::ExitStatus),
+    /// The worker thread of a `DatabaseHandle` stopped, or a closure run on
+    /// it panicked.
+    WorkerError(String),
     UnspecifiedError,
 }
 
@@ -19,6 +24,8 @@ impl fmt::Display for Error {
             Error::IoError(e) => e.fmt(f),
             Error::NotmuchError(e) => e.fmt(f),
             Error::NotmuchVerboseError(e, msg) => write!(f, "{} {}", e, msg),
+            Error::HookError(hook, status) => write!(f, "{} hook failed: {}", hook, status),
+            Error::WorkerError(msg) => write!(f, "database worker failed: {}", msg),
             Error::UnspecifiedError => write!(f, "Generic notmuch error"),
         }
     }
@@ -30,6 +37,8 @@ impl error::Error for Error {
             Error::IoError(e) => Some(e),
             Error::NotmuchError(e) => Some(e),
             Error::NotmuchVerboseError(e, _) => Some(e),
+            Error::HookError(_, _) => None,
+            Error::WorkerError(_) => None,
             Error::UnspecifiedError => None,
         }
     }
@@ -188,6 +188,15 @@ notmuch_enum! {
     }
 }
 
+notmuch_enum! {
+    #[repr(C)]
+    #[derive(Debug, Eq, PartialEq, Clone, Copy)]
+    pub enum notmuch_query_syntax_t => QuerySyntax {
+        NOTMUCH_QUERY_SYNTAX_XAPIAN => Xapian,
+        NOTMUCH_QUERY_SYNTAX_SEXP => Sexp
+    }
+}
+
 #[repr(C)]
 pub struct notmuch_database_t(c_void);
 #[repr(C)]
@@ -799,6 +808,30 @@ extern "C" {
         query_string: *const c_char,
     ) -> *mut notmuch_query_t;
 
+    /// Create a new query for 'database', using the given query syntax.
+    ///
+    /// Like `notmuch_query_create`, but the query string is parsed either
+    /// with the Xapian based infix parser, or with the S-expression parser
+    /// described in `notmuch-sexp-queries(7)`.
+    ///
+    /// Return value:
+    ///
+    /// * `notmuch_status_t::SUCCESS`: Successfully created the query.
+    ///
+    /// * `notmuch_status_t::BAD_QUERY_SYNTAX`: The query string could not be
+    ///   parsed.
+    ///
+    /// * `notmuch_status_t::ILLEGAL_ARGUMENT`: The requested syntax is not
+    ///   supported, e.g. sexp queries in a build without sfsexp.
+    ///
+    /// @since libnotmuch 5.5 (notmuch 0.34)
+    pub fn notmuch_query_create_with_syntax(
+        database: *mut notmuch_database_t,
+        query_string: *const c_char,
+        syntax: notmuch_query_syntax_t,
+        output: *mut *mut notmuch_query_t,
+    ) -> notmuch_status_t;
+
     /// Return the query_string of this query. See `notmuch_query_create`.
     pub fn notmuch_query_get_query_string(query: *mut notmuch_query_t) -> *const c_char;
 
@@ -0,0 +1,379 @@
+use std::ffi::CStr;
+use std::io::Write;
+use std::mem;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use libc;
+
+use error::{Error, Result};
+use ffi::{MessageFlag, Status};
+use headers::decode_header;
+use sprinter::{write_json, write_sexp, write_text0, Value};
+use Message;
+use Messages;
+use MimePart;
+use QueryExpr;
+use Tags;
+use Thread;
+use Threads;
+
+/// Options shared by the output formats.
+#[derive(Clone, Copy, Debug)]
+pub struct FormatOptions {
+    /// The schema version, as passed to `notmuch --format-version`.
+    pub format_version: u32,
+    /// Include the MIME parts of messages shown.
+    pub body: bool,
+    /// Include the content of `text/html` parts, which is omitted by
+    /// default like in `notmuch show`.
+    pub include_html: bool,
+}
+
+impl FormatOptions {
+    /// The newest schema version, the one written by default.
+    pub const CURRENT_VERSION: u32 = 5;
+    /// The oldest schema version still supported.
+    pub const MIN_VERSION: u32 = 4;
+}
+
+impl Default for FormatOptions {
+    fn default() -> Self {
+        FormatOptions {
+            format_version: FormatOptions::CURRENT_VERSION,
+            body: true,
+            include_html: false,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Format {
+    Json,
+    Sexp,
+    Text0,
+}
+
+/// Writes search results and messages in the structured output formats of
+/// the `notmuch` command line tool, for frontends that parse them.
+///
+/// All formats are written from the same intermediate structure, so they
+/// carry the same fields.
+#[derive(Clone, Debug)]
+pub struct Formatter {
+    format: Format,
+    options: FormatOptions,
+}
+
+fn localtime(time: i64) -> libc::tm {
+    let time = time as libc::time_t;
+    unsafe {
+        let mut tm: libc::tm = mem::zeroed();
+        libc::localtime_r(&time, &mut tm);
+        tm
+    }
+}
+
+fn strftime(format: &CStr, tm: &libc::tm) -> String {
+    let mut buf = [0 as libc::c_char; 64];
+    let len = unsafe { libc::strftime(buf.as_mut_ptr(), buf.len(), format.as_ptr(), tm) };
+    let bytes: Vec<u8> = buf[..len].iter().map(|&c| c as u8).collect();
+    String::from_utf8_lossy(&bytes).into_owned()
+}
+
+/// Describe `then` relative to `now` in local time, exactly like notmuch's
+/// `date_relative` fields.
+pub(crate) fn relative_date(then: i64, now: i64) -> String {
+    const DAY: i64 = 24 * 60 * 60;
+
+    if then > now {
+        return "the future".to_string();
+    }
+
+    let delta = now - then;
+    let tm_then = localtime(then);
+    let tm_now = localtime(now);
+    let cstr = |s: &'static [u8]| CStr::from_bytes_with_nul(s).unwrap();
+
+    if delta > 180 * DAY {
+        return strftime(cstr(b"%F\0"), &tm_then);
+    }
+    if delta < 3600 {
+        return format!("{} mins. ago", delta / 60);
+    }
+    if delta <= 7 * DAY {
+        if tm_then.tm_wday == tm_now.tm_wday && delta < DAY {
+            return strftime(cstr(b"Today %R\0"), &tm_then);
+        } else if (tm_now.tm_wday + 7 - tm_then.tm_wday) % 7 == 1 {
+            return strftime(cstr(b"Yest. %R\0"), &tm_then);
+        } else if tm_then.tm_wday != tm_now.tm_wday {
+            return strftime(cstr(b"%a. %R\0"), &tm_then);
+        }
+    }
+    strftime(cstr(b"%B %d\0"), &tm_then)
+}
+
+fn now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0)
+}
+
+/// A query matching exactly the given messages, or `null` if there are
+/// none.
+fn ids_query(ids: &[String]) -> Value {
+    if ids.is_empty() {
+        return Value::Null;
+    }
+    let terms: Vec<String> = ids.iter().map(|id| QueryExpr::id(id).to_string()).collect();
+    Value::String(terms.join(" or "))
+}
+
+/// The headers of a message, as `notmuch show` lists them.
+fn headers_value(part: &MimePart) -> Value {
+    let mut headers = vec![(
+        "Subject",
+        Value::String(part.header("subject").map(decode_header).unwrap_or_default()),
+    )];
+    headers.push(("From", Value::String(part.header("from").map(decode_header).unwrap_or_default())));
+    for &(key, name) in &[("To", "to"), ("Cc", "cc"), ("Bcc", "bcc"), ("Reply-To", "reply-to")] {
+        if let Some(value) = part.header(name) {
+            headers.push((key, Value::String(decode_header(value))));
+        }
+    }
+    headers.push(("Date", Value::String(part.header("date").unwrap_or_default().to_string())));
+    Value::Map(headers)
+}
+
+impl Formatter {
+    /// A formatter for `--format=json`.
+    pub fn json(options: FormatOptions) -> Result<Self> {
+        Formatter::new(Format::Json, options)
+    }
+
+    /// A formatter for `--format=sexp`, as used by the Emacs frontend.
+    pub fn sexp(options: FormatOptions) -> Result<Self> {
+        Formatter::new(Format::Sexp, options)
+    }
+
+    /// A formatter for `notmuch search --format=text0`, which writes bare
+    /// items terminated by a NUL byte: `thread:<id>` for threads,
+    /// `id:<message-id>` for messages and plain tags and filenames.
+    ///
+    /// Like in notmuch, text0 cannot be used to show messages.
+    pub fn text0(options: FormatOptions) -> Result<Self> {
+        Formatter::new(Format::Text0, options)
+    }
+
+    fn new(format: Format, options: FormatOptions) -> Result<Self> {
+        if options.format_version < FormatOptions::MIN_VERSION || options.format_version > FormatOptions::CURRENT_VERSION
+        {
+            return Err(Error::NotmuchVerboseError(
+                Status::IllegalArgument,
+                format!(
+                    "format version {} is not supported, only {} to {} are",
+                    options.format_version,
+                    FormatOptions::MIN_VERSION,
+                    FormatOptions::CURRENT_VERSION
+                ),
+            ));
+        }
+
+        Ok(Formatter { format, options })
+    }
+
+    fn write<W: Write>(&self, mut writer: W, value: &Value) -> Result<()> {
+        match self.format {
+            Format::Json => write_json(&mut writer, value)?,
+            Format::Sexp => write_sexp(&mut writer, value)?,
+            Format::Text0 => write_text0(&mut writer, value)?,
+        }
+        Ok(())
+    }
+
+    /// Write thread summaries, like `notmuch search --output=summary`.
+    ///
+    /// text0 has no room for summaries, so it only gets the thread ids, as
+    /// with `--output=threads`.
+    pub fn search_threads<W: Write>(&self, threads: Threads, writer: W) -> Result<()> {
+        if self.format == Format::Text0 {
+            let ids = threads.map(|thread| format!("thread:{}", thread.id()));
+            return self.write(writer, &Value::strings(ids));
+        }
+
+        let now = now();
+        let summaries = threads.map(|thread| self.thread_summary(&thread, now)).collect();
+        self.write(writer, &Value::List(summaries))
+    }
+
+    /// Write message ids, like `notmuch search --output=messages`.
+    pub fn search_messages<W: Write>(&self, messages: Messages, writer: W) -> Result<()> {
+        let ids = messages.map(|message| match self.format {
+            Format::Text0 => format!("id:{}", message.id()),
+            _ => message.id().into_owned(),
+        });
+        self.write(writer, &Value::strings(ids))
+    }
+
+    /// Write tags, like `notmuch search --output=tags`.
+    pub fn search_tags<W: Write>(&self, tags: Tags, writer: W) -> Result<()> {
+        self.write(writer, &Value::strings(tags))
+    }
+
+    /// Write the files of messages, like `notmuch search --output=files`.
+    pub fn search_files<W: Write>(&self, messages: Messages, writer: W) -> Result<()> {
+        let files = messages.flat_map(|message| {
+            message
+                .filenames()
+                .map(|filename| filename.to_string_lossy().into_owned())
+                .collect::<Vec<_>>()
+        });
+        self.write(writer, &Value::strings(files))
+    }
+
+    /// Write threads with all their messages, like `notmuch show`.
+    pub fn show_threads<W: Write>(&self, threads: Threads, writer: W) -> Result<()> {
+        self.check_show()?;
+        let threads = threads
+            .map(|thread| self.thread_value(&thread))
+            .collect::<Result<Vec<_>>>()?;
+        self.write(writer, &Value::List(threads))
+    }
+
+    /// Write a single thread of `notmuch show`.
+    pub fn show_thread<W: Write>(&self, thread: &Thread, writer: W) -> Result<()> {
+        self.check_show()?;
+        let thread = self.thread_value(thread)?;
+        self.write(writer, &thread)
+    }
+
+    /// Write a single message, like `notmuch show --part=0`.
+    pub fn show_message<W: Write>(&self, message: &Message, writer: W) -> Result<()> {
+        self.check_show()?;
+        let message = self.message_value(message, now())?;
+        self.write(writer, &message)
+    }
+
+    fn check_show(&self) -> Result<()> {
+        if self.format == Format::Text0 {
+            return Err(Error::NotmuchVerboseError(
+                Status::IllegalArgument,
+                "text0 cannot be used to show messages".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    fn thread_summary(&self, thread: &Thread, now: i64) -> Value {
+        let (matched, unmatched): (Vec<Message>, Vec<Message>) =
+            thread.messages().partition(|message| message.get_flag(MessageFlag::Match));
+        let ids = |messages: Vec<Message>| -> Vec<String> { messages.iter().map(|m| m.id().into_owned()).collect() };
+
+        let timestamp = thread.newest_date();
+        Value::Map(vec![
+            ("thread", Value::string(thread.id())),
+            ("timestamp", Value::Int(timestamp)),
+            ("date_relative", Value::String(relative_date(timestamp, now))),
+            ("matched", Value::Int(i64::from(thread.matched_messages()))),
+            ("total", Value::Int(i64::from(thread.total_messages()))),
+            ("authors", Value::String(thread.authors().join(","))),
+            ("subject", Value::string(thread.subject())),
+            ("query", Value::List(vec![ids_query(&ids(matched)), ids_query(&ids(unmatched))])),
+            ("tags", Value::strings(thread.tags())),
+        ])
+    }
+
+    fn thread_value(&self, thread: &Thread) -> Result<Value> {
+        let now = now();
+        let nodes = thread
+            .toplevel_messages()
+            .map(|message| self.node_value(&message, now))
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Value::List(nodes))
+    }
+
+    /// A message and its replies, as `[message, [replies]]`.
+    fn node_value(&self, message: &Message, now: i64) -> Result<Value> {
+        let replies = message
+            .replies()
+            .map(|reply| self.node_value(&reply, now))
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Value::List(vec![self.message_value(message, now)?, Value::List(replies)]))
+    }
+
+    fn message_value(&self, message: &Message, now: i64) -> Result<Value> {
+        let root = message.parts()?;
+        let timestamp = message.date();
+
+        let mut fields = vec![
+            ("id", Value::string(message.id())),
+            ("match", Value::Bool(message.get_flag(MessageFlag::Match))),
+            ("excluded", Value::Bool(message.get_flag(MessageFlag::Excluded))),
+            (
+                "filename",
+                Value::strings(message.filenames().map(|f| f.to_string_lossy().into_owned())),
+            ),
+            ("timestamp", Value::Int(timestamp)),
+            ("date_relative", Value::String(relative_date(timestamp, now))),
+            ("tags", Value::strings(message.tags())),
+        ];
+        if self.options.body {
+            fields.push(("body", Value::List(vec![self.part_value(&root)])));
+        }
+        if self.options.format_version >= 5 {
+            // Decryption and signature verification are not supported, so
+            // there is never any crypto status to report.
+            fields.push(("crypto", Value::Map(vec![])));
+        }
+        fields.push(("headers", headers_value(&root)));
+
+        Ok(Value::Map(fields))
+    }
+
+    fn part_value(&self, part: &MimePart) -> Value {
+        let mut fields = vec![
+            ("id", Value::Int(part.id as i64)),
+            ("content-type", Value::string(part.content_type.as_str())),
+        ];
+        if let Some(disposition) = &part.disposition {
+            fields.push(("content-disposition", Value::string(disposition.as_str())));
+        }
+        if let Some(content_id) = &part.content_id {
+            fields.push(("content-id", Value::string(content_id.as_str())));
+        }
+        if let Some(filename) = &part.filename {
+            fields.push(("filename", Value::string(filename.as_str())));
+        }
+
+        if part.is_multipart() {
+            let children = part.children.iter().map(|child| self.part_value(child)).collect();
+            fields.push(("content", Value::List(children)));
+        } else if part.content_type == "message/rfc822" && !part.children.is_empty() {
+            let inner = &part.children[0];
+            fields.push((
+                "content",
+                Value::List(vec![Value::Map(vec![
+                    ("headers", headers_value(inner)),
+                    ("body", Value::List(vec![self.part_value(inner)])),
+                ])]),
+            ));
+        } else if part.content_type.starts_with("text/")
+            && (self.options.include_html || part.content_type != "text/html")
+        {
+            let text = part.text().unwrap_or_default().replace("\r\n", "\n");
+            fields.push(("content", Value::String(text)));
+        } else {
+            // Like notmuch, report what a client needs to fetch and decode
+            // the content itself.
+            if let Some(charset) = &part.charset {
+                fields.push(("content-charset", Value::string(charset.as_str())));
+            }
+            if let Some(encoding) = part.header("content-transfer-encoding") {
+                fields.push(("content-transfer-encoding", Value::string(encoding)));
+            }
+            fields.push(("content-length", Value::Int(part.raw_len() as i64)));
+        }
+
+        Value::Map(fields)
+    }
+}
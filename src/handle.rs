@@ -0,0 +1,190 @@
+use std::any::Any;
+#[cfg(feature = "v0_32")]
+use std::path::Path;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::mpsc::{self, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+
+use error::{Error, Result};
+use Database;
+#[cfg(feature = "v0_32")]
+use DatabaseMode;
+use MessageSummary;
+use TagOps;
+use ThreadSummary;
+
+type Job = Box<dyn FnOnce(&Database) + Send>;
+
+/// The thread owning the database. Dropping it waits for the jobs already
+/// sent to finish.
+struct Worker {
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Drop for Worker {
+    fn drop(&mut self) {
+        if let Some(thread) = self.thread.take() {
+            // A job may hold the last handle, and a thread can't join itself.
+            if thread.thread().id() != thread::current().id() {
+                let _ = thread.join();
+            }
+        }
+    }
+}
+
+fn worker_gone() -> Error {
+    Error::WorkerError("the worker thread has stopped".to_string())
+}
+
+fn panic_message(payload: Box<dyn Any + Send>) -> String {
+    if let Some(msg) = payload.downcast_ref::<&str>() {
+        msg.to_string()
+    } else if let Some(msg) = payload.downcast_ref::<String>() {
+        msg.clone()
+    } else {
+        "panic in database worker".to_string()
+    }
+}
+
+/// A `Database` that can be shared between threads.
+///
+/// `Database` and everything obtained from it can't leave the thread it was
+/// opened on, since neither libnotmuch nor the `Rc`s tracking its pointers
+/// are thread safe. A `DatabaseHandle` instead opens the database on a
+/// thread of its own and runs everything there, one job at a time. Jobs
+/// return owned values, such as the snapshots of `Message::snapshot`.
+///
+/// Clones share the same database. The worker thread stops, and the
+/// database is closed, when the last clone is dropped.
+#[derive(Clone)]
+pub struct DatabaseHandle {
+    sender: Arc<Mutex<Sender<Job>>>,
+    // Declared after `sender` so that the channel is closed before the
+    // worker is joined.
+    _worker: Arc<Worker>,
+}
+
+impl DatabaseHandle {
+    /// Start a worker thread and open the database on it with `open`.
+    pub fn spawn<F>(open: F) -> Result<Self>
+    where
+        F: FnOnce() -> Result<Database> + Send + 'static,
+    {
+        let (sender, receiver) = mpsc::channel::<Job>();
+        let (opened_sender, opened) = mpsc::channel();
+
+        let thread = thread::Builder::new()
+            .name("notmuch-database".to_string())
+            .spawn(move || {
+                let database = match open() {
+                    Ok(database) => database,
+                    Err(err) => {
+                        let _ = opened_sender.send(Err(err));
+                        return;
+                    }
+                };
+                let _ = opened_sender.send(Ok(()));
+
+                for job in receiver {
+                    job(&database);
+                }
+            })?;
+        let worker = Worker { thread: Some(thread) };
+
+        opened.recv().map_err(|_| worker_gone())??;
+
+        Ok(DatabaseHandle {
+            sender: Arc::new(Mutex::new(sender)),
+            _worker: Arc::new(worker),
+        })
+    }
+
+    /// Open a database on a worker thread, see `Database::open_with_config`.
+    #[cfg(feature = "v0_32")]
+    pub fn open_with_config<DP, CP>(
+        database_path: Option<DP>,
+        mode: DatabaseMode,
+        config_path: Option<CP>,
+        profile: Option<&str>,
+    ) -> Result<Self>
+    where
+        DP: AsRef<Path>,
+        CP: AsRef<Path>,
+    {
+        let database_path = database_path.map(|p| p.as_ref().to_path_buf());
+        let config_path = config_path.map(|p| p.as_ref().to_path_buf());
+        let profile = profile.map(str::to_string);
+
+        DatabaseHandle::spawn(move || {
+            Database::open_with_config(database_path, mode, config_path, profile.as_deref())
+        })
+    }
+
+    /// Run `f` with the database on the worker thread and wait for its
+    /// result. A panic in `f` is returned as `Error::WorkerError`, and the
+    /// worker carries on.
+    pub fn run<F, T>(&self, f: F) -> Result<T>
+    where
+        F: FnOnce(&Database) -> Result<T> + Send + 'static,
+        T: Send + 'static,
+    {
+        let (result_sender, result) = mpsc::channel();
+        let job: Job = Box::new(move |database| {
+            let res = panic::catch_unwind(AssertUnwindSafe(|| f(database)))
+                .unwrap_or_else(|payload| Err(Error::WorkerError(panic_message(payload))));
+            let _ = result_sender.send(res);
+        });
+
+        self.sender
+            .lock()
+            .map_err(|_| worker_gone())?
+            .send(job)
+            .map_err(|_| worker_gone())?;
+
+        result.recv().map_err(|_| worker_gone())?
+    }
+
+    pub fn count_messages(&self, query: &str) -> Result<u32> {
+        let query = query.to_string();
+        self.run(move |database| database.create_query(&query)?.count_messages())
+    }
+
+    /// The messages matching `query`, in the default sort order.
+    pub fn search_messages(&self, query: &str) -> Result<Vec<MessageSummary>> {
+        let query = query.to_string();
+        self.run(move |database| {
+            database
+                .create_query(&query)?
+                .search_messages()?
+                .map(|message| message.snapshot())
+                .collect()
+        })
+    }
+
+    /// The threads matching `query`, with all of their messages.
+    pub fn search_threads(&self, query: &str) -> Result<Vec<ThreadSummary>> {
+        let query = query.to_string();
+        self.run(move |database| {
+            database
+                .create_query(&query)?
+                .search_threads()?
+                .map(|thread| thread.snapshot())
+                .collect()
+        })
+    }
+
+    pub fn find_message(&self, message_id: &str) -> Result<Option<MessageSummary>> {
+        let message_id = message_id.to_string();
+        self.run(move |database| match database.find_message(&message_id)? {
+            Some(message) => message.snapshot().map(Some),
+            None => Ok(None),
+        })
+    }
+
+    /// Apply tag changes to all messages matching `query`.
+    pub fn tag(&self, query: &str, ops: TagOps) -> Result<()> {
+        let query = query.to_string();
+        self.run(move |database| database.create_query(&query)?.apply_tags(&ops))
+    }
+}
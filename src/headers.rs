@@ -0,0 +1,460 @@
+use std::fmt;
+use std::mem;
+
+use error::Result;
+use utils::{base64_decode, decode_charset};
+use Message;
+
+/// A mailbox from an address header, such as `From` or `To`.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Address {
+    /// The display name, with encoded words decoded.
+    pub name: Option<String>,
+    /// The `local@domain` part.
+    pub address: String,
+}
+
+impl Address {
+    pub fn new(name: Option<&str>, address: &str) -> Self {
+        Address {
+            name: name.map(|n| n.to_string()),
+            address: address.to_string(),
+        }
+    }
+
+    /// Parse an address list header value, as in RFC 5322 section 3.4.
+    ///
+    /// Groups are flattened into their members. Parsing is lenient: what
+    /// can't be made sense of is skipped rather than failing the list.
+    pub fn parse_list(value: &str) -> Vec<Address> {
+        let mut parser = AddressParser::default();
+        let mut chars = value.chars().peekable();
+
+        while let Some(c) = chars.next() {
+            match c {
+                '"' => {
+                    let mut word = String::new();
+                    while let Some(c) = chars.next() {
+                        match c {
+                            '"' => break,
+                            '\\' => word.extend(chars.next()),
+                            c => word.push(c),
+                        }
+                    }
+                    parser.words.push(word);
+                }
+                '(' => {
+                    let mut depth = 1;
+                    let mut comment = String::new();
+                    while let Some(c) = chars.next() {
+                        match c {
+                            '(' => depth += 1,
+                            ')' => {
+                                depth -= 1;
+                                if depth == 0 {
+                                    break;
+                                }
+                            }
+                            '\\' => {
+                                comment.extend(chars.next());
+                                continue;
+                            }
+                            _ => {}
+                        }
+                        comment.push(c);
+                    }
+                    parser.comment = Some(comment);
+                }
+                '<' => {
+                    let mut angle = String::new();
+                    for c in chars.by_ref() {
+                        match c {
+                            '>' => break,
+                            c if c.is_whitespace() => {}
+                            c => angle.push(c),
+                        }
+                    }
+                    parser.angle = Some(angle);
+                }
+                ',' => parser.finish(),
+                ':' => {
+                    // The phrase so far named a group.
+                    parser.words.clear();
+                    parser.comment = None;
+                }
+                ';' => parser.finish(),
+                c if c.is_whitespace() => {}
+                c => {
+                    let mut word = c.to_string();
+                    while let Some(&c) = chars.peek() {
+                        if c.is_whitespace() || "\"(<,:;".contains(c) {
+                            break;
+                        }
+                        word.push(c);
+                        chars.next();
+                    }
+                    parser.words.push(word);
+                }
+            }
+        }
+        parser.finish();
+
+        parser.addresses
+    }
+}
+
+impl fmt::Display for Address {
+    /// Format the address as it would appear in a header, without encoding
+    /// non-ASCII names.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.name {
+            Some(name) if name.chars().all(|c| c.is_alphanumeric() || " .-'".contains(c)) => {
+                write!(f, "{} <{}>", name, self.address)
+            }
+            Some(name) => write!(
+                f,
+                "\"{}\" <{}>",
+                name.replace('\\', "\\\\").replace('"', "\\\""),
+                self.address
+            ),
+            None => f.write_str(&self.address),
+        }
+    }
+}
+
+#[derive(Default)]
+struct AddressParser {
+    addresses: Vec<Address>,
+    /// The atoms and quoted strings of the current mailbox.
+    words: Vec<String>,
+    angle: Option<String>,
+    comment: Option<String>,
+}
+
+impl AddressParser {
+    /// End the current mailbox. Its words are the display name if an angle
+    /// address follows, and the address itself otherwise.
+    fn finish(&mut self) {
+        let words = mem::take(&mut self.words);
+        let angle = self.angle.take();
+        let comment = self.comment.take();
+
+        let (name, address) = match angle {
+            Some(address) => {
+                let name = words.join(" ");
+                (if name.is_empty() { comment } else { Some(name) }, address)
+            }
+            None => (comment, words.concat()),
+        };
+
+        if address.is_empty() {
+            return;
+        }
+
+        let name = name
+            .map(|n| decode_header(n.trim()))
+            .filter(|n| !n.is_empty());
+        self.addresses.push(Address { name, address });
+    }
+}
+
+/// A message id, without the enclosing angle brackets, as notmuch stores
+/// it.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct MessageId(pub String);
+
+impl MessageId {
+    /// Parse all `<id>` tokens of a `References` or `In-Reply-To` header,
+    /// ignoring anything in between.
+    pub fn parse_list(value: &str) -> Vec<MessageId> {
+        let mut ids = vec![];
+        let mut rest = value;
+        while let Some(start) = rest.find('<') {
+            let after = &rest[start + 1..];
+            match after.find('>') {
+                Some(end) => {
+                    let id: String = after[..end].chars().filter(|c| !c.is_whitespace()).collect();
+                    if !id.is_empty() {
+                        ids.push(MessageId(id));
+                    }
+                    rest = &after[end + 1..];
+                }
+                None => break,
+            }
+        }
+        ids
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Display for MessageId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// The date of a `Date` header, keeping the timezone of the sender.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct HeaderDate {
+    /// Seconds since the epoch.
+    pub timestamp: i64,
+    /// Offset of the sender's timezone from UTC, in minutes.
+    pub offset: i32,
+}
+
+impl HeaderDate {
+    /// Parse an RFC 5322 date, including the obsolete forms of section
+    /// 4.3: two digit years, named zones and comments.
+    pub fn parse(value: &str) -> Option<HeaderDate> {
+        let mut cleaned = String::with_capacity(value.len());
+        let mut depth = 0;
+        for c in value.chars() {
+            match c {
+                '(' => depth += 1,
+                ')' if depth > 0 => depth -= 1,
+                _ if depth > 0 => {}
+                ',' => cleaned.push(' '),
+                c => cleaned.push(c),
+            }
+        }
+
+        let mut tokens: Vec<&str> = cleaned.split_whitespace().collect();
+        if tokens.first().is_some_and(|t| t.chars().all(|c| c.is_ascii_alphabetic())) {
+            tokens.remove(0);
+        }
+        if tokens.len() < 4 {
+            return None;
+        }
+
+        let day: i64 = tokens[0].parse().ok()?;
+        let month = parse_month(tokens[1])?;
+        let year = match (tokens[2].len(), tokens[2].parse::<i64>().ok()?) {
+            (2, y) if y < 50 => 2000 + y,
+            (2, y) | (3, y) => 1900 + y,
+            (_, y) => y,
+        };
+
+        let mut time = tokens[3].split(':').map(|t| t.parse::<i64>());
+        let hour = time.next()?.ok()?;
+        let minute = time.next()?.ok()?;
+        let second = match time.next() {
+            Some(s) => s.ok()?,
+            None => 0,
+        };
+        if !(1..=31).contains(&day) || hour > 23 || minute > 59 || second > 60 {
+            return None;
+        }
+
+        let offset = tokens.get(4).map_or(Some(0), |zone| parse_zone(zone))?;
+
+        let days = days_from_civil(year, month, day);
+        let timestamp = days * 86400 + hour * 3600 + minute * 60 + second - i64::from(offset) * 60;
+
+        Some(HeaderDate { timestamp, offset })
+    }
+}
+
+fn parse_month(name: &str) -> Option<i64> {
+    let months = [
+        "jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec",
+    ];
+    let name = name.get(..3)?.to_ascii_lowercase();
+    months.iter().position(|m| *m == name).map(|m| m as i64 + 1)
+}
+
+/// The offset of a zone, in minutes.
+fn parse_zone(zone: &str) -> Option<i32> {
+    let sign = match zone.chars().next()? {
+        '+' => 1,
+        '-' => -1,
+        _ => {
+            return match zone.to_ascii_uppercase().as_str() {
+                "UT" | "UTC" | "GMT" | "Z" => Some(0),
+                "EDT" => Some(-4 * 60),
+                "EST" | "CDT" => Some(-5 * 60),
+                "CST" | "MDT" => Some(-6 * 60),
+                "MST" | "PDT" => Some(-7 * 60),
+                "PST" => Some(-8 * 60),
+                // Military zones are too ambiguous to rely on.
+                z if z.len() == 1 => Some(0),
+                _ => None,
+            };
+        }
+    };
+
+    let digits = &zone[1..];
+    if digits.len() != 4 || !digits.bytes().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    let hours: i32 = digits[..2].parse().ok()?;
+    let minutes: i32 = digits[2..].parse().ok()?;
+    Some(sign * (hours * 60 + minutes))
+}
+
+/// Days since the epoch of a proleptic Gregorian date.
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = if year >= 0 { year } else { year - 399 } / 400;
+    let yoe = year - era * 400;
+    let mp = (month + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
+
+/// Decode a single `=?charset?encoding?text?=` word.
+fn decode_word(word: &str) -> Option<String> {
+    let inner = word.get(2..word.len().checked_sub(2)?)?;
+    let mut parts = inner.splitn(3, '?');
+    let charset = parts.next()?;
+    let encoding = parts.next()?;
+    let text = parts.next()?;
+
+    // RFC 2231 allows a language after the charset.
+    let charset = charset.split('*').next()?;
+
+    let bytes = match encoding {
+        "B" | "b" => base64_decode(text)?,
+        "Q" | "q" => {
+            let mut bytes = vec![];
+            let mut chars = text.bytes();
+            while let Some(c) = chars.next() {
+                match c {
+                    b'_' => bytes.push(b' '),
+                    b'=' => {
+                        let hex = [chars.next()?, chars.next()?];
+                        let hex = std::str::from_utf8(&hex).ok()?;
+                        bytes.push(u8::from_str_radix(hex, 16).ok()?);
+                    }
+                    c => bytes.push(c),
+                }
+            }
+            bytes
+        }
+        _ => return None,
+    };
+
+    decode_charset(charset, &bytes)
+}
+
+/// Decode the RFC 2047 encoded words in a header value. Whitespace between
+/// adjacent encoded words is dropped; words that can't be decoded are kept
+/// as they are.
+pub fn decode_header(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    let mut rest = value;
+    // Whitespace after a decoded word, held back until we know whether
+    // another encoded word follows.
+    let mut pending_space: Option<&str> = None;
+
+    while !rest.is_empty() {
+        let start = match rest.find("=?") {
+            Some(start) => start,
+            None => break,
+        };
+
+        // An encoded word is `=?charset?encoding?text?=`, without spaces.
+        let end = rest[start + 2..]
+            .match_indices("?=")
+            .map(|(i, _)| start + 2 + i + 2)
+            .find(|&end| rest[start..end].matches('?').count() >= 4);
+        let word = end.map(|end| &rest[start..end]).filter(|w| !w.contains(char::is_whitespace));
+
+        match word.and_then(decode_word) {
+            Some(decoded) => {
+                let before = &rest[..start];
+                match pending_space {
+                    Some(space) if !before.trim().is_empty() => {
+                        out.push_str(space);
+                        out.push_str(before);
+                    }
+                    Some(_) => {}
+                    None => out.push_str(before),
+                }
+                out.push_str(&decoded);
+                rest = &rest[start + word.unwrap().len()..];
+
+                let trimmed = rest.trim_start();
+                pending_space = Some(&rest[..rest.len() - trimmed.len()]);
+                rest = trimmed;
+            }
+            None => {
+                if let Some(space) = pending_space.take() {
+                    out.push_str(space);
+                }
+                out.push_str(&rest[..start + 2]);
+                rest = &rest[start + 2..];
+                continue;
+            }
+        }
+    }
+
+    if let Some(space) = pending_space {
+        out.push_str(space);
+    }
+    out.push_str(rest);
+    out
+}
+
+impl Message {
+    fn header_string(&self, name: &str) -> Result<Option<String>> {
+        Ok(self.header(name)?.map(|v| v.into_owned()))
+    }
+
+    fn address_header(&self, name: &str) -> Result<Vec<Address>> {
+        Ok(self
+            .header_string(name)?
+            .map(|v| Address::parse_list(&v))
+            .unwrap_or_default())
+    }
+
+    /// The addresses of the `From` header.
+    pub fn from(&self) -> Result<Vec<Address>> {
+        self.address_header("from")
+    }
+
+    pub fn to(&self) -> Result<Vec<Address>> {
+        self.address_header("to")
+    }
+
+    pub fn cc(&self) -> Result<Vec<Address>> {
+        self.address_header("cc")
+    }
+
+    pub fn bcc(&self) -> Result<Vec<Address>> {
+        self.address_header("bcc")
+    }
+
+    pub fn reply_to(&self) -> Result<Vec<Address>> {
+        self.address_header("reply-to")
+    }
+
+    /// The decoded `Subject` header.
+    pub fn subject(&self) -> Result<Option<String>> {
+        Ok(self.header_string("subject")?.map(|v| decode_header(&v)))
+    }
+
+    /// The message ids of the `References` header, oldest first.
+    pub fn references(&self) -> Result<Vec<MessageId>> {
+        Ok(self
+            .header_string("references")?
+            .map(|v| MessageId::parse_list(&v))
+            .unwrap_or_default())
+    }
+
+    /// The first message id of the `In-Reply-To` header.
+    pub fn in_reply_to(&self) -> Result<Option<MessageId>> {
+        Ok(self
+            .header_string("in-reply-to")?
+            .and_then(|v| MessageId::parse_list(&v).into_iter().next()))
+    }
+
+    /// The parsed `Date` header. Unlike `date()`, this keeps the timezone
+    /// of the sender.
+    pub fn date_header(&self) -> Result<Option<HeaderDate>> {
+        Ok(self.header_string("date")?.and_then(|v| HeaderDate::parse(&v)))
+    }
+}
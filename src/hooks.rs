@@ -0,0 +1,138 @@
+use std::fs;
+use std::io;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use error::{Error, Result};
+use ffi::ConfigKey;
+use Database;
+use ScanReport;
+
+/// The hooks notmuch knows about, see `notmuch-hooks(5)`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Hook {
+    /// Run before looking for new mail.
+    PreNew,
+    /// Run after new mail was indexed.
+    PostNew,
+    /// Run after a message was delivered with `Database::insert`.
+    PostInsert,
+}
+
+impl Hook {
+    /// The file name of the hook within the hook directory.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Hook::PreNew => "pre-new",
+            Hook::PostNew => "post-new",
+            Hook::PostInsert => "post-insert",
+        }
+    }
+}
+
+/// Runs the hook scripts of a database.
+///
+/// A hook that doesn't exist is silently skipped, and one that isn't
+/// executable is skipped with a warning on stderr, like `notmuch` does.
+#[derive(Clone, Debug)]
+pub struct Hooks {
+    dir: PathBuf,
+    working_dir: PathBuf,
+    config_path: Option<PathBuf>,
+}
+
+impl Hooks {
+    /// Hooks found in `dir`, run from `working_dir`.
+    pub fn new<D, W>(dir: D, working_dir: W) -> Self
+    where
+        D: AsRef<Path>,
+        W: AsRef<Path>,
+    {
+        Hooks {
+            dir: dir.as_ref().to_path_buf(),
+            working_dir: working_dir.as_ref().to_path_buf(),
+            config_path: None,
+        }
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// The path of the script for `hook`, whether it exists or not.
+    pub fn path(&self, hook: Hook) -> PathBuf {
+        self.dir.join(hook.name())
+    }
+
+    /// Run `hook`, failing if it can't be started or exits unsuccessfully.
+    ///
+    /// `NOTMUCH_CONFIG` is set to the configuration file of the database, if
+    /// any, so that `notmuch` commands in the hook use the same database.
+    pub fn run(&self, hook: Hook) -> Result<()> {
+        let path = self.path(hook);
+
+        let metadata = match fs::metadata(&path) {
+            Ok(metadata) => metadata,
+            Err(ref err) if err.kind() == io::ErrorKind::NotFound => return Ok(()),
+            Err(err) => return Err(err.into()),
+        };
+        if !metadata.is_file() || metadata.permissions().mode() & 0o111 == 0 {
+            eprintln!("Warning: {} hook not executable, ignoring", hook.name());
+            return Ok(());
+        }
+
+        let mut command = Command::new(&path);
+        command.current_dir(&self.working_dir);
+        if let Some(config_path) = &self.config_path {
+            command.env("NOTMUCH_CONFIG", config_path);
+        }
+
+        let status = command.status()?;
+        if status.success() {
+            Ok(())
+        } else {
+            Err(Error::HookError(hook.name().to_string(), status))
+        }
+    }
+}
+
+impl Database {
+    /// The hooks of this database, found in `database.hook_dir` or, if that
+    /// is not set, in `.notmuch/hooks`. They are run from the mail root.
+    pub fn hooks(&self) -> Hooks {
+        let dir = self
+            .config(ConfigKey::HookDir)
+            .filter(|dir| !dir.is_empty())
+            .map(PathBuf::from)
+            .unwrap_or_else(|| self.path().join(".notmuch").join("hooks"));
+
+        let working_dir = self
+            .config(ConfigKey::MailRoot)
+            .map(PathBuf::from)
+            .unwrap_or_else(|| self.path().to_path_buf());
+
+        Hooks {
+            dir,
+            working_dir,
+            config_path: self.config_path().map(Path::to_path_buf),
+        }
+    }
+
+    /// `scan_new`, surrounded by the `pre-new` and `post-new` hooks, like
+    /// `notmuch new` does.
+    ///
+    /// As with `notmuch new`, the database is closed before running
+    /// `post-new`, so that the hook can modify it. Reopen the database to
+    /// keep using it.
+    pub fn scan_new_with_hooks(&self) -> Result<ScanReport> {
+        let hooks = self.hooks();
+
+        hooks.run(Hook::PreNew)?;
+        let report = self.scan_new()?;
+        self.close()?;
+        hooks.run(Hook::PostNew)?;
+
+        Ok(report)
+    }
+}
@@ -0,0 +1,199 @@
+use std::ffi::CStr;
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::path::{Component, Path, PathBuf};
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use libc;
+
+use error::{Error, Result};
+use ffi::{ConfigKey, Status};
+use AtomicOperation;
+use Database;
+use Hook;
+use Message;
+use MessageSummary;
+use TagOps;
+
+/// How to deliver a message with `Database::insert`.
+#[derive(Clone, Debug, Default)]
+pub struct InsertOptions {
+    /// Create the folder as a maildir if it doesn't exist.
+    pub create_folder: bool,
+    /// Keep the file if it can't be indexed, instead of removing it again.
+    pub keep: bool,
+    /// Tag changes applied after `new.tags`.
+    pub tags: TagOps,
+    /// Run the `post-insert` hook. As with `notmuch insert`, the database is
+    /// closed before running it.
+    pub run_hooks: bool,
+}
+
+fn insert_error(status: Status, reason: String) -> Error {
+    Error::NotmuchVerboseError(status, reason)
+}
+
+/// Check that `folder` stays within the mail root.
+fn validate_folder(folder: &str) -> Result<&Path> {
+    let path = Path::new(folder);
+    let valid = path
+        .components()
+        .all(|c| matches!(c, Component::Normal(_) | Component::CurDir));
+
+    if valid {
+        Ok(path)
+    } else {
+        Err(insert_error(
+            Status::IllegalArgument,
+            format!("invalid folder name '{}'", folder),
+        ))
+    }
+}
+
+fn hostname() -> String {
+    let mut buf = [0 as libc::c_char; 256];
+    let ret = unsafe { libc::gethostname(buf.as_mut_ptr(), buf.len() - 1) };
+    if ret != 0 {
+        return "localhost".to_string();
+    }
+
+    // Slashes and colons would break the maildir naming scheme.
+    unsafe { CStr::from_ptr(buf.as_ptr()) }
+        .to_string_lossy()
+        .replace('/', "\\057")
+        .replace(':', "\\072")
+}
+
+/// A file name that is unique within a maildir, as recommended by the
+/// maildir specification.
+fn unique_name() -> String {
+    static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+    format!(
+        "{}.M{}P{}Q{}.{}",
+        now.as_secs(),
+        now.subsec_micros(),
+        process::id(),
+        COUNTER.fetch_add(1, Ordering::SeqCst),
+        hostname()
+    )
+}
+
+fn sync_dir(path: &Path) -> Result<()> {
+    File::open(path)?.sync_all()?;
+    Ok(())
+}
+
+/// Make sure `dir` is a maildir, creating it if asked to.
+fn prepare_maildir(dir: &Path, create: bool) -> Result<()> {
+    for sub in &["cur", "new", "tmp"] {
+        let path = dir.join(sub);
+        if path.is_dir() {
+            continue;
+        }
+        if !create {
+            return Err(insert_error(
+                Status::PathError,
+                format!("{} is not a maildir", dir.display()),
+            ));
+        }
+        fs::create_dir_all(&path)?;
+    }
+    Ok(())
+}
+
+/// Write `bytes` to `tmp/` of the maildir `dir`, then move it to `new/`,
+/// syncing along the way so the message survives a crash once this
+/// returns.
+fn deliver(dir: &Path, bytes: &[u8]) -> Result<PathBuf> {
+    let name = unique_name();
+    let tmp_path = dir.join("tmp").join(&name);
+    let new_path = dir.join("new").join(&name);
+
+    let result = OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&tmp_path)
+        .and_then(|mut file| {
+            file.write_all(bytes)?;
+            file.sync_all()
+        })
+        .and_then(|_| fs::rename(&tmp_path, &new_path));
+
+    if let Err(err) = result {
+        let _ = fs::remove_file(&tmp_path);
+        return Err(err.into());
+    }
+
+    sync_dir(&dir.join("new"))?;
+    Ok(new_path)
+}
+
+impl Database {
+    /// Deliver a message to `folder`, relative to the mail root, and index
+    /// it, like `notmuch insert` does.
+    ///
+    /// The message is tagged with `new.tags` and then `options.tags`. If
+    /// `maildir.synchronize_flags` is set, the tags are synchronized to
+    /// maildir flags, which may move the file to `cur/`.
+    ///
+    /// If the message can't be indexed, the file is removed again unless
+    /// `options.keep` is set.
+    ///
+    /// Returns a snapshot of the message as indexed, since the database may
+    /// be closed by the time this returns.
+    pub fn insert(&self, bytes: &[u8], folder: &str, options: InsertOptions) -> Result<MessageSummary> {
+        let root = self
+            .config(ConfigKey::MailRoot)
+            .map(PathBuf::from)
+            .unwrap_or_else(|| self.path().to_path_buf());
+        let dir = root.join(validate_folder(folder)?);
+
+        prepare_maildir(&dir, options.create_folder)?;
+        let path = deliver(&dir, bytes)?;
+
+        let hooks = self.hooks();
+
+        let message = match self
+            .index_delivered(&path, &options)
+            .and_then(|message| message.snapshot())
+        {
+            Ok(message) => message,
+            Err(err) => {
+                if !options.keep {
+                    let _ = self.remove_message(&path);
+                    let _ = fs::remove_file(&path);
+                }
+                return Err(err);
+            }
+        };
+
+        if options.run_hooks {
+            self.close()?;
+            hooks.run(Hook::PostInsert)?;
+        }
+
+        Ok(message)
+    }
+
+    fn index_delivered(&self, path: &Path, options: &InsertOptions) -> Result<Message> {
+        let _atomic = AtomicOperation::new(self)?;
+
+        let message = self.index_file(path, None)?;
+
+        let mut ops = TagOps::new();
+        if let Some(new_tags) = self.config_values(ConfigKey::NewTags) {
+            for tag in new_tags.filter(|t| !t.is_empty()) {
+                ops = ops.with_add(&tag);
+            }
+        }
+        ops.ops.extend(options.tags.ops.iter().cloned());
+        ops.remove_all = options.tags.remove_all;
+        message.apply(&ops)?;
+
+        Ok(message)
+    }
+}
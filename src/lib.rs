@@ -5,41 +5,104 @@
 mod macros;
 
 extern crate from_variants;
+extern crate encoding_rs;
 extern crate libc;
+extern crate regex;
+#[cfg(feature = "watch")]
+extern crate inotify;
+#[cfg(feature = "serde")]
+extern crate serde;
 
 mod ffi;
 mod utils;
 
+mod addresses;
+mod attachment;
+mod body;
+#[cfg(feature = "v0_21")]
+mod changes;
 mod config_list;
 mod config_pairs;
 mod config_values;
 mod database;
 mod directory;
+mod dump;
 mod error;
 mod filenames;
+mod format;
+mod handle;
+mod headers;
+#[cfg(feature = "v0_32")]
+mod hooks;
 mod index_opts;
+#[cfg(feature = "v0_32")]
+mod insert;
 mod message;
 mod message_properties;
 mod messages;
+mod mime;
+mod named_queries;
+mod page;
 mod query;
+mod query_expr;
+mod query_parser;
+#[cfg(feature = "v0_32")]
+mod reply;
+#[cfg(feature = "v0_32")]
+mod scan;
+mod snapshot;
+mod sprinter;
+mod tag_ops;
 mod tags;
 mod thread;
+mod thread_tree;
 mod threads;
+#[cfg(feature = "watch")]
+mod watch;
 
+pub use addresses::{AddressCount, AddressOptions, AddressOrder, Deduplicate};
+pub use attachment::Attachment;
+pub use body::BodyOptions;
+#[cfg(feature = "v0_21")]
+pub use changes::Changes;
 pub use config_list::ConfigList;
 pub use config_pairs::ConfigPairs;
 pub use config_values::ConfigValues;
 pub use database::{AtomicOperation, Database, Revision};
 pub use directory::Directory;
+pub use dump::{DumpOptions, RestoreOptions};
 pub use error::Error;
 pub use filenames::Filenames;
+pub use format::{FormatOptions, Formatter};
+pub use handle::DatabaseHandle;
+pub use headers::{decode_header, Address, HeaderDate, MessageId};
+#[cfg(feature = "v0_32")]
+pub use hooks::{Hook, Hooks};
 pub use index_opts::IndexOpts;
+#[cfg(feature = "v0_32")]
+pub use insert::InsertOptions;
 pub use message::{FrozenMessage, Message};
 pub use message_properties::MessageProperties;
 pub use messages::Messages;
+pub use mime::MimePart;
+pub use named_queries::NamedQueries;
+pub use page::{Cursor, Page};
 pub use query::Query;
+pub use query_expr::{Field, QueryExpr, RangeField};
+pub use query_parser::{normalize_query, parse_query, ParsedQuery, QueryParseError, QueryWarning};
+#[cfg(feature = "v0_32")]
+pub use reply::{ReplyTemplate, ReplyTo};
+#[cfg(feature = "v0_32")]
+pub use scan::ScanReport;
+pub use snapshot::{MessageSummary, PropertyMap, ThreadSummary};
+pub use tag_ops::{TagOp, TagOps};
 pub use tags::Tags;
 pub use thread::Thread;
+pub use thread_tree::{PostOrder, PreOrder, ThreadNode};
 pub use threads::Threads;
+#[cfg(feature = "watch")]
+pub use watch::{WatchEvent, Watcher};
 
-pub use ffi::{ConfigKey, DatabaseMode, DecryptionPolicy, Exclude, MessageFlag, Sort, Status};
+pub use ffi::{
+    ConfigKey, DatabaseMode, DecryptionPolicy, Exclude, MessageFlag, QuerySyntax, Sort, Status,
+};
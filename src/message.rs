@@ -54,6 +54,16 @@ impl Message {
         }
     }
 
+    /// The database the message belongs to, found through its owners.
+    pub(crate) fn database(&self) -> &Database {
+        match &*self.owner {
+            MessageOwner::Database(database) => database,
+            MessageOwner::Messages(messages) => messages.database(),
+            MessageOwner::Thread(thread) => thread.database(),
+            MessageOwner::Query(query) => query.database(),
+        }
+    }
+
     pub fn id(&self) -> Cow<'_, str> {
         let mid = unsafe { ffi::notmuch_message_get_message_id(self.ptr.0) };
         mid.to_string_lossy()
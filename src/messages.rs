@@ -6,6 +6,7 @@ use std::rc::Rc;
 use from_variants::FromVariants;
 
 use ffi;
+use Database;
 use Query;
 use Thread;
 use Message;
@@ -44,6 +45,15 @@ impl Messages {
             owner: Box::new(owner.into()),
         }
     }
+
+    pub(crate) fn database(&self) -> &Database {
+        match &*self.owner {
+            MessagesOwner::Query(query) => query.database(),
+            MessagesOwner::Message(message) => message.database(),
+            MessagesOwner::Messages(messages) => messages.database(),
+            MessagesOwner::Thread(thread) => thread.database(),
+        }
+    }
 }
 
 impl Messages {
@@ -68,6 +78,19 @@ impl Messages {
     }
 }
 
+impl Messages {
+    /// Move past the next `n` messages without looking at them, returning
+    /// how many were actually skipped.
+    pub(crate) fn skip_n(&mut self, n: usize) -> usize {
+        let mut skipped = 0;
+        while skipped < n && unsafe { ffi::notmuch_messages_valid(self.ptr.0) } != 0 {
+            unsafe { ffi::notmuch_messages_move_to_next(self.ptr.0) };
+            skipped += 1;
+        }
+        skipped
+    }
+}
+
 impl Iterator for Messages {
     type Item = Message;
 
@@ -0,0 +1,510 @@
+use std::fmt;
+use std::fs;
+use std::io::{self, Write};
+use std::ops::Range;
+use std::sync::Arc;
+
+use error::{Error, Result};
+use ffi::Status;
+use headers::decode_header;
+use utils::{decode_charset, quoted_printable_decode};
+use Message;
+
+/// Nesting beyond this depth is kept as an opaque part, so that malicious
+/// messages can't exhaust the stack.
+const MAX_DEPTH: usize = 64;
+
+/// A node of the MIME tree of a message.
+///
+/// Parts are numbered depth-first starting at 1 for the whole message, the
+/// same way `notmuch show --part` numbers them. The only child of a
+/// `message/rfc822` part is the top part of the enclosed message.
+///
+/// Parts share the bytes of the message and only decode their body when
+/// asked to.
+#[derive(Clone, PartialEq, Eq)]
+pub struct MimePart {
+    pub id: usize,
+    /// The header fields of the part, in order, with unfolded values.
+    pub headers: Vec<(String, String)>,
+    /// The lowercase `type/subtype`, `text/plain` if not given.
+    pub content_type: String,
+    pub charset: Option<String>,
+    /// The lowercase disposition type, e.g. `attachment` or `inline`.
+    pub disposition: Option<String>,
+    pub filename: Option<String>,
+    pub content_id: Option<String>,
+    pub children: Vec<MimePart>,
+    encoding: String,
+    source: Arc<Vec<u8>>,
+    body_range: Range<usize>,
+}
+
+impl fmt::Debug for MimePart {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        // Leave out the bytes of the message, which all parts share.
+        f.debug_struct("MimePart")
+            .field("id", &self.id)
+            .field("headers", &self.headers)
+            .field("content_type", &self.content_type)
+            .field("charset", &self.charset)
+            .field("disposition", &self.disposition)
+            .field("filename", &self.filename)
+            .field("content_id", &self.content_id)
+            .field("children", &self.children)
+            .field("encoding", &self.encoding)
+            .field("body_range", &self.body_range)
+            .finish()
+    }
+}
+
+impl MimePart {
+    /// Parse a complete message.
+    pub fn parse(bytes: &[u8]) -> MimePart {
+        MimePart::parse_owned(bytes.to_vec())
+    }
+
+    fn parse_owned(bytes: Vec<u8>) -> MimePart {
+        let source = Arc::new(bytes);
+        let mut next_id = 1;
+        parse_part(&source, 0..source.len(), "text/plain", &mut next_id, 0)
+    }
+
+    /// The value of the first header field called `name`, case insensitive.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+
+    pub fn is_multipart(&self) -> bool {
+        self.content_type.starts_with("multipart/")
+    }
+
+    /// The body with the transfer encoding removed. Empty for multipart
+    /// parts, the whole enclosed message for `message/rfc822` parts.
+    pub fn body(&self) -> Vec<u8> {
+        let mut body = Vec::with_capacity(self.raw_len());
+        // Writing to a `Vec` can't fail.
+        let _ = self.write_body(&mut body);
+        body
+    }
+
+    /// Write the body with the transfer encoding removed to `writer`, a
+    /// chunk at a time, returning the number of bytes written.
+    ///
+    /// As mail clients do, characters that don't belong in base64 are
+    /// skipped rather than failing the whole body.
+    pub fn write_body<W: Write>(&self, writer: &mut W) -> io::Result<usize> {
+        let raw = self.raw_body();
+        match self.encoding.as_str() {
+            "base64" => write_base64(raw, writer),
+            "quoted-printable" => {
+                let mut written = 0;
+                // Escapes and soft line breaks never span lines.
+                for line in raw.split_inclusive(|&b| b == b'\n') {
+                    let decoded = quoted_printable_decode(line);
+                    writer.write_all(&decoded)?;
+                    written += decoded.len();
+                }
+                Ok(written)
+            }
+            _ => {
+                writer.write_all(raw)?;
+                Ok(raw.len())
+            }
+        }
+    }
+
+    fn raw_body(&self) -> &[u8] {
+        &self.source[self.body_range.clone()]
+    }
+
+    /// The body of a `text/*` part, decoded to a string. Unknown charsets
+    /// are read as UTF-8.
+    pub fn text(&self) -> Option<String> {
+        if !self.content_type.starts_with("text/") {
+            return None;
+        }
+
+        let body = self.body();
+        let charset = self.charset.as_ref().map_or("us-ascii", String::as_str);
+        Some(decode_charset(charset, &body).unwrap_or_else(|| String::from_utf8_lossy(&body).into_owned()))
+    }
+
+    /// The size of the body as it is in the message, before decoding.
+    pub(crate) fn raw_len(&self) -> usize {
+        self.body_range.len()
+    }
+
+    /// Whether the part is meant to be saved rather than displayed.
+    pub fn is_attachment(&self) -> bool {
+        self.disposition.as_ref().is_some_and(|d| d == "attachment")
+            || (self.filename.is_some() && !self.is_multipart())
+    }
+
+    /// This part and all parts below it, in id order.
+    pub fn walk(&self) -> Vec<&MimePart> {
+        let mut parts = vec![self];
+        for child in &self.children {
+            parts.extend(child.walk());
+        }
+        parts
+    }
+
+    /// The part with the given id, if it is this one or below it.
+    pub fn find(&self, id: usize) -> Option<&MimePart> {
+        self.walk().into_iter().find(|p| p.id == id)
+    }
+}
+
+/// Decode lenient base64 from `raw` to `writer`, skipping anything outside
+/// the alphabet and stopping at the padding.
+fn write_base64<W: Write>(raw: &[u8], writer: &mut W) -> io::Result<usize> {
+    let mut chunk = [0u8; 4096];
+    let mut len = 0;
+    let mut written = 0;
+    let mut acc: u32 = 0;
+    let mut bits = 0;
+
+    for &c in raw {
+        let value = match c {
+            b'A'..=b'Z' => c - b'A',
+            b'a'..=b'z' => c - b'a' + 26,
+            b'0'..=b'9' => c - b'0' + 52,
+            b'+' => 62,
+            b'/' => 63,
+            b'=' => break,
+            _ => continue,
+        };
+        acc = (acc << 6) | u32::from(value);
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            chunk[len] = (acc >> bits) as u8;
+            len += 1;
+            if len == chunk.len() {
+                writer.write_all(&chunk)?;
+                written += len;
+                len = 0;
+            }
+        }
+    }
+
+    writer.write_all(&chunk[..len])?;
+    Ok(written + len)
+}
+
+/// Split a part at the empty line ending its header, returning the length
+/// of the header and where the body starts.
+fn split_header(bytes: &[u8]) -> (usize, usize) {
+    if bytes.starts_with(b"\r\n") {
+        return (0, 2);
+    }
+    if bytes.starts_with(b"\n") {
+        return (0, 1);
+    }
+
+    let mut i = 0;
+    while let Some(pos) = bytes[i..].iter().position(|&b| b == b'\n') {
+        let end = i + pos + 1;
+        let rest = &bytes[end..];
+        if rest.starts_with(b"\r\n") {
+            return (end, end + 2);
+        }
+        if rest.starts_with(b"\n") {
+            return (end, end + 1);
+        }
+        i = end;
+    }
+    (bytes.len(), bytes.len())
+}
+
+fn parse_headers(bytes: &[u8]) -> Vec<(String, String)> {
+    let text = String::from_utf8_lossy(bytes);
+    let mut headers: Vec<(String, String)> = vec![];
+
+    for line in text.lines() {
+        let line = line.trim_end_matches('\r');
+        if line.starts_with(' ') || line.starts_with('\t') {
+            if let Some((_, value)) = headers.last_mut() {
+                value.push(' ');
+                value.push_str(line.trim());
+            }
+            continue;
+        }
+        if let Some(colon) = line.find(':') {
+            let name = line[..colon].trim();
+            if !name.is_empty() {
+                headers.push((name.to_string(), line[colon + 1..].trim().to_string()));
+            }
+        }
+    }
+
+    headers
+}
+
+/// Split a structured header value such as `Content-Type` into its main
+/// value and parameters, joining RFC 2231 continuations and decoding RFC
+/// 2231 and RFC 2047 encoded values.
+fn parse_params(value: &str) -> (String, Vec<(String, String)>) {
+    let mut segments = vec![];
+    let mut current = String::new();
+    let mut in_quotes = false;
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => in_quotes = !in_quotes,
+            '\\' if in_quotes => current.extend(chars.next()),
+            ';' if !in_quotes => segments.push(std::mem::take(&mut current)),
+            c => current.push(c),
+        }
+    }
+    segments.push(current);
+
+    let main = segments.remove(0).trim().to_ascii_lowercase();
+
+    // (name, section, encoded, value)
+    let mut raw: Vec<(String, usize, bool, String)> = vec![];
+    for segment in segments {
+        let mut kv = segment.splitn(2, '=');
+        let (key, value) = match (kv.next(), kv.next()) {
+            (Some(key), Some(value)) => (key.trim().to_ascii_lowercase(), value.trim().to_string()),
+            _ => continue,
+        };
+
+        let encoded = key.ends_with('*');
+        let key = key.trim_end_matches('*');
+        let (name, section) = match key.rfind('*') {
+            Some(star) => match key[star + 1..].parse() {
+                Ok(section) => (key[..star].to_string(), section),
+                Err(_) => (key.to_string(), 0),
+            },
+            None => (key.to_string(), 0),
+        };
+        raw.push((name, section, encoded, value));
+    }
+    raw.sort_by(|a, b| (&a.0, a.1).cmp(&(&b.0, b.1)));
+
+    let mut params: Vec<(String, String)> = vec![];
+    let mut charset = None;
+    for (name, section, encoded, value) in raw {
+        let value = if encoded {
+            let mut value = value.as_str();
+            if section == 0 {
+                let mut parts = value.splitn(3, '\'');
+                if let (Some(cs), Some(_lang), Some(rest)) = (parts.next(), parts.next(), parts.next()) {
+                    charset = Some(cs.to_string());
+                    value = rest;
+                }
+            }
+            let bytes = percent_decode(value);
+            charset
+                .as_ref()
+                .and_then(|cs| decode_charset(cs, &bytes))
+                .unwrap_or_else(|| String::from_utf8_lossy(&bytes).into_owned())
+        } else {
+            decode_header(&value)
+        };
+
+        match params.last_mut() {
+            Some((last, joined)) if *last == name && section > 0 => joined.push_str(&value),
+            _ => params.push((name, value)),
+        }
+    }
+
+    (main, params)
+}
+
+/// Decode the `%XX` escapes of an RFC 2231 value, keeping malformed ones.
+fn percent_decode(value: &str) -> Vec<u8> {
+    let bytes = value.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = value
+            .get(i + 1..i + 3)
+            .filter(|_| bytes[i] == b'%')
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match escaped {
+            Some(b) => {
+                out.push(b);
+                i += 3;
+            }
+            None => {
+                out.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    out
+}
+
+fn param<'a>(params: &'a [(String, String)], name: &str) -> Option<&'a str> {
+    params.iter().find(|(n, _)| n == name).map(|(_, v)| v.as_str())
+}
+
+/// Split a multipart body at its boundaries, dropping the preamble and
+/// epilogue. Returns the ranges of the parts within `body`.
+fn split_multipart(body: &[u8], boundary: &str) -> Vec<Range<usize>> {
+    let delimiter = format!("--{}", boundary);
+    let delimiter = delimiter.as_bytes();
+
+    let mut parts = vec![];
+    let mut start: Option<usize> = None;
+    let mut line_start = 0;
+    while line_start < body.len() {
+        let line_end = body[line_start..]
+            .iter()
+            .position(|&b| b == b'\n')
+            .map_or(body.len(), |p| line_start + p + 1);
+        let line = &body[line_start..line_end];
+
+        if line.starts_with(delimiter) {
+            let rest = &line[delimiter.len()..];
+            let is_close = rest.starts_with(b"--");
+            let rest = if is_close { &rest[2..] } else { rest };
+
+            if rest.iter().all(|b| b.is_ascii_whitespace()) {
+                if let Some(s) = start {
+                    // The line break before the delimiter belongs to it.
+                    let mut end = line_start.max(s);
+                    if end > s && body[end - 1] == b'\n' {
+                        end -= 1;
+                        if end > s && body[end - 1] == b'\r' {
+                            end -= 1;
+                        }
+                    }
+                    parts.push(s..end);
+                }
+                if is_close {
+                    return parts;
+                }
+                start = Some(line_end);
+            }
+        }
+
+        line_start = line_end;
+    }
+
+    // A missing close delimiter ends the last part at the end of the body.
+    if let Some(s) = start {
+        parts.push(s..body.len());
+    }
+    parts
+}
+
+/// Whether a body with this transfer encoding is stored as it is.
+fn is_identity(encoding: &str) -> bool {
+    matches!(encoding, "" | "7bit" | "8bit" | "binary")
+}
+
+/// Parse the part at `range` of `source`.
+fn parse_part(
+    source: &Arc<Vec<u8>>,
+    range: Range<usize>,
+    default_type: &str,
+    next_id: &mut usize,
+    depth: usize,
+) -> MimePart {
+    let id = *next_id;
+    *next_id += 1;
+
+    let bytes = &source[range.clone()];
+    let (header_len, body_start) = split_header(bytes);
+    let headers = parse_headers(&bytes[..header_len]);
+    let body = range.start + body_start..range.end;
+    let find = |name: &str| {
+        headers
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.clone())
+    };
+
+    let (content_type, type_params) = match find("content-type") {
+        Some(value) => parse_params(&value),
+        None => (default_type.to_string(), vec![]),
+    };
+    let content_type = if content_type.contains('/') {
+        content_type
+    } else {
+        "text/plain".to_string()
+    };
+
+    let (disposition, disposition_params) = match find("content-disposition") {
+        Some(value) => {
+            let (disposition, params) = parse_params(&value);
+            (Some(disposition), params)
+        }
+        None => (None, vec![]),
+    };
+
+    let filename = param(&disposition_params, "filename")
+        .or_else(|| param(&type_params, "name"))
+        .map(str::to_string);
+
+    let encoding = find("content-transfer-encoding")
+        .map(|e| e.trim().to_ascii_lowercase())
+        .unwrap_or_default();
+
+    let mut part = MimePart {
+        id,
+        content_type,
+        charset: param(&type_params, "charset").map(str::to_string),
+        disposition,
+        filename,
+        content_id: find("content-id").map(|c| c.trim_matches(|c| c == '<' || c == '>' || c == ' ').to_string()),
+        headers: vec![],
+        children: vec![],
+        encoding,
+        source: source.clone(),
+        body_range: body.start..body.start,
+    };
+    if !part.is_multipart() {
+        part.body_range = body.clone();
+    }
+
+    if depth < MAX_DEPTH {
+        if part.is_multipart() {
+            if let Some(boundary) = param(&type_params, "boundary") {
+                let child_type = if part.content_type == "multipart/digest" {
+                    "message/rfc822"
+                } else {
+                    "text/plain"
+                };
+                part.children = split_multipart(&source[body.clone()], boundary)
+                    .into_iter()
+                    .map(|r| {
+                        let range = body.start + r.start..body.start + r.end;
+                        parse_part(source, range, child_type, next_id, depth + 1)
+                    })
+                    .collect();
+            }
+        } else if part.content_type == "message/rfc822" {
+            // RFC 2046 doesn't allow encoding enclosed messages, but some
+            // senders do. Only those are decoded to a buffer of their own.
+            let child = if is_identity(&part.encoding) {
+                parse_part(source, body, "text/plain", next_id, depth + 1)
+            } else {
+                let inner = Arc::new(part.body());
+                parse_part(&inner, 0..inner.len(), "text/plain", next_id, depth + 1)
+            };
+            part.children = vec![child];
+        }
+    }
+
+    part.headers = headers;
+    part
+}
+
+impl Message {
+    /// The MIME structure of the message, read from its first file.
+    pub fn parts(&self) -> Result<MimePart> {
+        let filename = self.filenames().next().ok_or_else(|| {
+            Error::NotmuchVerboseError(Status::FileError, format!("message {} has no file", self.id()))
+        })?;
+
+        Ok(MimePart::parse_owned(fs::read(filename)?))
+    }
+}
@@ -0,0 +1,143 @@
+use error::{Error, Result};
+use ffi::Status;
+use Database;
+use Query;
+
+const PREFIX: &str = "query.";
+const REFERENCE: &str = "query:";
+
+/// The saved searches of a database, stored under the `query.` prefix of
+/// its configuration and referred to as `query:<name>` in queries.
+#[derive(Clone, Debug)]
+pub struct NamedQueries {
+    database: Database,
+}
+
+fn named_query_error(reason: String) -> Error {
+    Error::NotmuchVerboseError(Status::IllegalArgument, reason)
+}
+
+impl NamedQueries {
+    /// All named queries as `(name, query)` pairs, sorted by name.
+    pub fn list(&self) -> Result<Vec<(String, String)>> {
+        let mut queries: Vec<(String, String)> = self
+            .database
+            .config_list(PREFIX)?
+            .filter(|(_, query)| !query.is_empty())
+            .map(|(key, query)| (key[PREFIX.len()..].to_string(), query))
+            .collect();
+        queries.sort();
+        Ok(queries)
+    }
+
+    /// The query string saved under `name`.
+    pub fn get(&self, name: &str) -> Result<Option<String>> {
+        let query = self.database.config_string(&format!("{}{}", PREFIX, name))?;
+        Ok(if query.is_empty() { None } else { Some(query) })
+    }
+
+    /// Save `query` under `name`, replacing any previous query of that name.
+    ///
+    /// The query is saved exactly as given; it is only parsed by notmuch
+    /// when used.
+    pub fn set(&self, name: &str, query: &str) -> Result<()> {
+        if name.is_empty() || name.contains(char::is_whitespace) {
+            return Err(named_query_error(format!("invalid query name '{}'", name)));
+        }
+        if query.is_empty() {
+            return Err(named_query_error(format!("empty query for '{}'", name)));
+        }
+
+        self.database.config_set_string(&format!("{}{}", PREFIX, name), query)
+    }
+
+    pub fn delete(&self, name: &str) -> Result<()> {
+        self.database.config_set_string(&format!("{}{}", PREFIX, name), "")
+    }
+
+    /// The query saved under `name`, with all `query:` references in it
+    /// replaced by the queries they name, in parentheses.
+    ///
+    /// Fails if a referenced query doesn't exist or if queries refer to
+    /// each other in a cycle.
+    pub fn expand(&self, name: &str) -> Result<String> {
+        self.expand_name(name, &mut vec![])
+    }
+
+    /// Expand all `query:` references of an arbitrary query string.
+    pub fn expand_query(&self, query: &str) -> Result<String> {
+        self.expand_refs(query, &mut vec![])
+    }
+
+    /// The expanded query saved under `name`, ready to run.
+    pub fn query(&self, name: &str) -> Result<Query> {
+        self.database.create_query(&self.expand(name)?)
+    }
+
+    fn expand_name(&self, name: &str, stack: &mut Vec<String>) -> Result<String> {
+        if stack.iter().any(|n| n == name) {
+            stack.push(name.to_string());
+            return Err(named_query_error(format!(
+                "named query cycle: {}",
+                stack.join(" -> ")
+            )));
+        }
+
+        let query = match self.get(name)? {
+            Some(query) => query,
+            None => return Err(named_query_error(format!("unknown named query '{}'", name))),
+        };
+
+        stack.push(name.to_string());
+        let expanded = self.expand_refs(&query, stack)?;
+        stack.pop();
+
+        Ok(expanded)
+    }
+
+    /// Replace the `query:name` terms of `query`, leaving everything else,
+    /// including quoted strings, untouched.
+    fn expand_refs(&self, query: &str, stack: &mut Vec<String>) -> Result<String> {
+        let mut expanded = String::with_capacity(query.len());
+        let mut rest = query;
+        let mut quoted = false;
+        let mut prev: Option<char> = None;
+
+        while let Some(c) = rest.chars().next() {
+            let starts_term = prev.map_or(true, |p| p.is_whitespace() || "(){+-".contains(p));
+            if !quoted && starts_term && rest.starts_with(REFERENCE) {
+                let name = &rest[REFERENCE.len()..];
+                let name = &name[..name
+                    .find(|c: char| c.is_whitespace() || c == ')' || c == '}')
+                    .unwrap_or(name.len())];
+
+                if !name.is_empty() {
+                    expanded.push('(');
+                    expanded.push_str(&self.expand_name(name, stack)?);
+                    expanded.push(')');
+                    rest = &rest[REFERENCE.len() + name.len()..];
+                    prev = Some(')');
+                    continue;
+                }
+            }
+
+            // Doubled quotes within a quoted string toggle twice.
+            if c == '"' {
+                quoted = !quoted;
+            }
+            expanded.push(c);
+            prev = Some(c);
+            rest = &rest[c.len_utf8()..];
+        }
+
+        Ok(expanded)
+    }
+}
+
+impl Database {
+    pub fn named_queries(&self) -> NamedQueries {
+        NamedQueries {
+            database: self.clone(),
+        }
+    }
+}
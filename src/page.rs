@@ -0,0 +1,112 @@
+use error::Result;
+use Message;
+use Messages;
+use Query;
+use Thread;
+use Threads;
+
+/// One page of search results.
+#[derive(Clone, Debug)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    /// Position of the first item within all results.
+    pub offset: usize,
+    /// Number of results of the whole search.
+    pub total: usize,
+}
+
+impl<T> Page<T> {
+    /// The offset of the next page, if there is one.
+    pub fn next_offset(&self) -> Option<usize> {
+        let next = self.offset + self.items.len();
+        if next < self.total {
+            Some(next)
+        } else {
+            None
+        }
+    }
+
+    pub fn has_more(&self) -> bool {
+        self.next_offset().is_some()
+    }
+}
+
+/// A search that is kept open, so that consecutive pages don't need to
+/// walk the results before them again.
+///
+/// Not `Clone`: clones would share the notmuch iterator, but not the
+/// position within it.
+#[derive(Debug)]
+pub struct Cursor<I> {
+    iter: I,
+    position: usize,
+}
+
+impl<I: Iterator> Cursor<I> {
+    /// The number of results consumed so far, i.e. the offset of the next
+    /// page.
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    /// The next `limit` results; fewer at the end of the search.
+    pub fn next_page(&mut self, limit: usize) -> Vec<I::Item> {
+        let items: Vec<I::Item> = self.iter.by_ref().take(limit).collect();
+        self.position += items.len();
+        items
+    }
+}
+
+impl<I: Iterator> Iterator for Cursor<I> {
+    type Item = I::Item;
+
+    fn next(&mut self) -> Option<I::Item> {
+        let item = self.iter.next();
+        if item.is_some() {
+            self.position += 1;
+        }
+        item
+    }
+}
+
+impl Query {
+    /// Search messages, starting at the `offset`th result.
+    pub fn search_messages_cursor(&self, offset: usize) -> Result<Cursor<Messages>> {
+        let mut messages = self.search_messages()?;
+        let position = messages.skip_n(offset);
+        Ok(Cursor {
+            iter: messages,
+            position,
+        })
+    }
+
+    /// Search threads, starting at the `offset`th result.
+    pub fn search_threads_cursor(&self, offset: usize) -> Result<Cursor<Threads>> {
+        let mut threads = self.search_threads()?;
+        let position = threads.skip_n(offset);
+        Ok(Cursor {
+            iter: threads,
+            position,
+        })
+    }
+
+    /// At most `limit` messages, starting at the `offset`th result.
+    pub fn search_messages_page(&self, offset: usize, limit: usize) -> Result<Page<Message>> {
+        let items = self.search_messages_cursor(offset)?.next_page(limit);
+        Ok(Page {
+            items,
+            offset,
+            total: self.count_messages()? as usize,
+        })
+    }
+
+    /// At most `limit` threads, starting at the `offset`th result.
+    pub fn search_threads_page(&self, offset: usize, limit: usize) -> Result<Page<Thread>> {
+        let items = self.search_threads_cursor(offset)?.next_page(limit);
+        Ok(Page {
+            items,
+            offset,
+            total: self.count_threads()? as usize,
+        })
+    }
+}
@@ -34,6 +34,12 @@ impl Query {
         }
     }
 
+    /// The database this query runs against.
+    pub(crate) fn database(&self) -> &Database
+    {
+        &self.owner
+    }
+
     pub fn create(database: &Database, query_string: &str) -> Result<Self>
     {
         database.create_query(query_string)
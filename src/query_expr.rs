@@ -0,0 +1,516 @@
+use std::borrow::Cow;
+use std::fmt;
+use std::ops;
+
+use error::Result;
+#[cfg(feature = "v0_34")]
+use ffi::QuerySyntax;
+use Database;
+use Query;
+
+/// A prefix of the notmuch query syntax, as in `tag:inbox`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Field {
+    Tag,
+    From,
+    To,
+    Subject,
+    Id,
+    Thread,
+    Folder,
+    Path,
+    Property,
+    MimeType,
+    Attachment,
+    Body,
+    Query,
+}
+
+impl Field {
+    /// All fields, in the order they are documented in
+    /// `notmuch-search-terms(7)`.
+    pub const ALL: &'static [Field] = &[
+        Field::Tag,
+        Field::From,
+        Field::To,
+        Field::Subject,
+        Field::Id,
+        Field::Thread,
+        Field::Folder,
+        Field::Path,
+        Field::Property,
+        Field::MimeType,
+        Field::Attachment,
+        Field::Body,
+        Field::Query,
+    ];
+
+    /// The prefix, without the trailing colon.
+    pub fn prefix(self) -> &'static str {
+        match self {
+            Field::Tag => "tag",
+            Field::From => "from",
+            Field::To => "to",
+            Field::Subject => "subject",
+            Field::Id => "id",
+            Field::Thread => "thread",
+            Field::Folder => "folder",
+            Field::Path => "path",
+            Field::Property => "property",
+            Field::MimeType => "mimetype",
+            Field::Attachment => "attachment",
+            Field::Body => "body",
+            Field::Query => "query",
+        }
+    }
+
+    /// Look up a prefix, including the aliases `is:` for `tag:` and `mid:`
+    /// for `id:`.
+    pub fn from_prefix(prefix: &str) -> Option<Field> {
+        match prefix {
+            "is" => Some(Field::Tag),
+            "mid" => Some(Field::Id),
+            _ => Field::ALL.iter().cloned().find(|f| f.prefix() == prefix),
+        }
+    }
+}
+
+/// A prefix of the notmuch query syntax that takes a `from..to` range.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum RangeField {
+    Date,
+    Lastmod,
+}
+
+impl RangeField {
+    pub fn prefix(self) -> &'static str {
+        match self {
+            RangeField::Date => "date",
+            RangeField::Lastmod => "lastmod",
+        }
+    }
+
+    pub fn from_prefix(prefix: &str) -> Option<RangeField> {
+        match prefix {
+            "date" => Some(RangeField::Date),
+            "lastmod" => Some(RangeField::Lastmod),
+            _ => None,
+        }
+    }
+}
+
+/// A notmuch query, built programmatically instead of by pasting strings.
+///
+/// The `Display` implementation renders the query in the (Xapian based)
+/// infix syntax accepted by `Database::create_query`, quoting values where
+/// needed:
+///
+/// ```ignore
+/// let expr = QueryExpr::tag("inbox") & !QueryExpr::from_addr("John \"JD\" Doe");
+/// assert_eq!(expr.to_string(), r#"tag:inbox and not from:"John ""JD"" Doe""#);
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum QueryExpr {
+    /// Matches all messages, `*`.
+    All,
+    /// Free text, matched as a phrase if it holds several words. A single
+    /// word ending in `*` is a wildcard.
+    Text(String),
+    Field(Field, String),
+    /// A range; an open end is `None`.
+    Range(RangeField, Option<String>, Option<String>),
+    And(Vec<QueryExpr>),
+    Or(Vec<QueryExpr>),
+    /// Matches if an odd number of `exprs` match.
+    Xor(Vec<QueryExpr>),
+    Not(Box<QueryExpr>),
+    /// Terms within a window of words, 10 by default, in any order.
+    Near(Vec<QueryExpr>, Option<u32>),
+    /// Like `Near`, but the terms must appear in the given order.
+    Adj(Vec<QueryExpr>, Option<u32>),
+    /// Messages in threads with a message matching the subquery, written
+    /// `thread:{...}`.
+    InThread(Box<QueryExpr>),
+    /// A query in the S-expression syntax, written `sexp:"..."`.
+    Sexp(String),
+    /// A prefix this crate doesn't know, such as one defined with
+    /// `index.header.*`, and its value.
+    Custom(String, String),
+}
+
+impl QueryExpr {
+    pub fn text(text: &str) -> Self {
+        QueryExpr::Text(text.to_string())
+    }
+
+    pub fn tag(tag: &str) -> Self {
+        QueryExpr::Field(Field::Tag, tag.to_string())
+    }
+
+    pub fn from_addr(address: &str) -> Self {
+        QueryExpr::Field(Field::From, address.to_string())
+    }
+
+    pub fn to_addr(address: &str) -> Self {
+        QueryExpr::Field(Field::To, address.to_string())
+    }
+
+    pub fn subject(subject: &str) -> Self {
+        QueryExpr::Field(Field::Subject, subject.to_string())
+    }
+
+    pub fn id(message_id: &str) -> Self {
+        QueryExpr::Field(Field::Id, message_id.to_string())
+    }
+
+    pub fn thread(thread_id: &str) -> Self {
+        QueryExpr::Field(Field::Thread, thread_id.to_string())
+    }
+
+    pub fn folder(folder: &str) -> Self {
+        QueryExpr::Field(Field::Folder, folder.to_string())
+    }
+
+    pub fn path(path: &str) -> Self {
+        QueryExpr::Field(Field::Path, path.to_string())
+    }
+
+    pub fn property(key: &str, value: &str) -> Self {
+        QueryExpr::Field(Field::Property, format!("{}={}", key, value))
+    }
+
+    /// Messages dated between `from` and `to`, in any format understood by
+    /// notmuch, e.g. `2019-01-31`, `yesterday` or `3 days ago`.
+    pub fn date(from: Option<&str>, to: Option<&str>) -> Self {
+        QueryExpr::Range(
+            RangeField::Date,
+            from.map(|s| s.to_string()),
+            to.map(|s| s.to_string()),
+        )
+    }
+
+    /// Messages last modified between the database revisions `from` and
+    /// `to`.
+    pub fn lastmod(from: Option<u64>, to: Option<u64>) -> Self {
+        QueryExpr::Range(
+            RangeField::Lastmod,
+            from.map(|r| r.to_string()),
+            to.map(|r| r.to_string()),
+        )
+    }
+
+    /// Matches if all of `exprs` match; an empty list matches everything.
+    pub fn and<I>(exprs: I) -> Self
+    where
+        I: IntoIterator<Item = QueryExpr>,
+    {
+        QueryExpr::And(exprs.into_iter().collect())
+    }
+
+    /// Matches if any of `exprs` matches; an empty list matches nothing.
+    pub fn or<I>(exprs: I) -> Self
+    where
+        I: IntoIterator<Item = QueryExpr>,
+    {
+        QueryExpr::Or(exprs.into_iter().collect())
+    }
+
+    /// Matches if an odd number of `exprs` match.
+    pub fn xor<I>(exprs: I) -> Self
+    where
+        I: IntoIterator<Item = QueryExpr>,
+    {
+        QueryExpr::Xor(exprs.into_iter().collect())
+    }
+
+    pub fn negate(expr: QueryExpr) -> Self {
+        QueryExpr::Not(Box::new(expr))
+    }
+
+    /// Messages in threads with a message matching `expr`.
+    pub fn in_thread(expr: QueryExpr) -> Self {
+        QueryExpr::InThread(Box::new(expr))
+    }
+
+    /// Whether this needs parentheses when nested in another expression.
+    fn is_compound(&self) -> bool {
+        match self {
+            QueryExpr::And(exprs) | QueryExpr::Or(exprs) | QueryExpr::Xor(exprs) => exprs.len() > 1,
+            _ => false,
+        }
+    }
+
+    fn fmt_nested(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.is_compound() {
+            write!(f, "({})", self)
+        } else {
+            write!(f, "{}", self)
+        }
+    }
+
+    fn fmt_list(f: &mut fmt::Formatter, exprs: &[QueryExpr], op: &str) -> fmt::Result {
+        for (i, expr) in exprs.iter().enumerate() {
+            if i > 0 {
+                write!(f, " {} ", op)?;
+            }
+            expr.fmt_nested(f)?;
+        }
+        Ok(())
+    }
+
+    fn fmt_proximity(f: &mut fmt::Formatter, terms: &[QueryExpr], op: &str, distance: Option<u32>) -> fmt::Result {
+        let op = match distance {
+            Some(distance) => format!("{}/{}", op, distance),
+            None => op.to_string(),
+        };
+        for (i, term) in terms.iter().enumerate() {
+            if i > 0 {
+                write!(f, " {} ", op)?;
+            }
+            write!(f, "{}", term)?;
+        }
+        Ok(())
+    }
+}
+
+/// Whether `value` can be written without quotes after a prefix or as a
+/// free text term. A `:` would turn a term into a prefixed one, and a
+/// leading `/` into a regex.
+fn is_plain(value: &str) -> bool {
+    !value.is_empty()
+        && !value.starts_with('-')
+        && !value.starts_with('+')
+        && !value.starts_with('/')
+        && value
+            .chars()
+            .all(|c| c.is_alphanumeric() || "@._-+=,/".contains(c))
+}
+
+/// Quote a free text term, keeping a trailing `*` wildcard working.
+fn quote_term(term: &str) -> Cow<'_, str> {
+    if term.len() > 1 && term.ends_with('*') && is_plain(&term[..term.len() - 1]) {
+        Cow::Borrowed(term)
+    } else {
+        quote(term)
+    }
+}
+
+/// Quote `value` for the Xapian query parser if needed. Quotes within a
+/// quoted string are doubled.
+pub(crate) fn quote(value: &str) -> Cow<'_, str> {
+    if is_plain(value) {
+        Cow::Borrowed(value)
+    } else {
+        Cow::Owned(format!("\"{}\"", value.replace('"', "\"\"")))
+    }
+}
+
+fn is_keyword(word: &str) -> bool {
+    matches!(
+        word.to_ascii_lowercase().as_str(),
+        "and" | "or" | "not" | "xor" | "near" | "adj"
+    )
+}
+
+impl fmt::Display for QueryExpr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            QueryExpr::All => f.write_str("*"),
+            QueryExpr::Text(text) => {
+                if is_keyword(text) {
+                    write!(f, "\"{}\"", text)
+                } else {
+                    f.write_str(&quote_term(text))
+                }
+            }
+            QueryExpr::Field(field, value) => write!(f, "{}:{}", field.prefix(), quote(value)),
+            QueryExpr::Custom(prefix, value) => write!(f, "{}:{}", prefix, quote_term(value)),
+            QueryExpr::InThread(expr) => write!(f, "thread:{{{}}}", expr),
+            QueryExpr::Sexp(sexp) => write!(f, "sexp:{}", quote(sexp)),
+            QueryExpr::Range(field, from, to) => {
+                let from = from.as_ref().map_or("", |s| s.as_str());
+                let to = to.as_ref().map_or("", |s| s.as_str());
+                if from.is_empty() && to.is_empty() {
+                    return f.write_str("*");
+                }
+
+                let range = format!("{}..{}", from, to);
+                if (from.is_empty() || is_plain(from)) && (to.is_empty() || is_plain(to)) {
+                    write!(f, "{}:{}", field.prefix(), range)
+                } else {
+                    write!(f, "{}:{}", field.prefix(), quote(&range))
+                }
+            }
+            QueryExpr::And(exprs) => {
+                if exprs.is_empty() {
+                    f.write_str("*")
+                } else {
+                    QueryExpr::fmt_list(f, exprs, "and")
+                }
+            }
+            QueryExpr::Or(exprs) => {
+                if exprs.is_empty() {
+                    f.write_str("not *")
+                } else {
+                    QueryExpr::fmt_list(f, exprs, "or")
+                }
+            }
+            QueryExpr::Xor(exprs) => {
+                if exprs.is_empty() {
+                    f.write_str("not *")
+                } else {
+                    QueryExpr::fmt_list(f, exprs, "xor")
+                }
+            }
+            QueryExpr::Near(terms, distance) => QueryExpr::fmt_proximity(f, terms, "near", *distance),
+            QueryExpr::Adj(terms, distance) => QueryExpr::fmt_proximity(f, terms, "adj", *distance),
+            QueryExpr::Not(expr) => {
+                f.write_str("not ")?;
+                // `near` and `adj` bind tighter than the binary operators,
+                // but only combine single terms.
+                match **expr {
+                    QueryExpr::Not(_) | QueryExpr::Near(..) | QueryExpr::Adj(..) => write!(f, "({})", expr),
+                    _ => expr.fmt_nested(f),
+                }
+            }
+        }
+    }
+}
+
+/// Quote `value` as an atom or string of the S-expression syntax.
+fn sexp_quote(value: &str) -> Cow<'_, str> {
+    if is_plain(value) && !is_keyword(value) {
+        Cow::Borrowed(value)
+    } else {
+        Cow::Owned(format!(
+            "\"{}\"",
+            value.replace('\\', "\\\\").replace('"', "\\\"")
+        ))
+    }
+}
+
+impl QueryExpr {
+    /// Render the query in the S-expression syntax described in
+    /// `notmuch-sexp-queries(7)`, which, unlike the infix syntax, has no
+    /// ambiguities.
+    pub fn to_sexp(&self) -> String {
+        let mut out = String::new();
+        self.write_sexp(&mut out);
+        out
+    }
+
+    fn write_sexp_list(out: &mut String, op: &str, exprs: &[QueryExpr]) {
+        out.push('(');
+        out.push_str(op);
+        for expr in exprs {
+            out.push(' ');
+            expr.write_sexp(out);
+        }
+        out.push(')');
+    }
+
+    fn write_sexp(&self, out: &mut String) {
+        match self {
+            QueryExpr::All => out.push_str("()"),
+            QueryExpr::Text(text) => out.push_str(&sexp_quote(text)),
+            QueryExpr::Field(field, value) => {
+                out.push('(');
+                out.push_str(field.prefix());
+                out.push(' ');
+                out.push_str(&sexp_quote(value));
+                out.push(')');
+            }
+            QueryExpr::Range(field, from, to) => {
+                out.push('(');
+                out.push_str(field.prefix());
+                for end in &[from, to] {
+                    out.push(' ');
+                    match end {
+                        Some(end) => out.push_str(&sexp_quote(end)),
+                        None => out.push('*'),
+                    }
+                }
+                out.push(')');
+            }
+            QueryExpr::And(exprs) if exprs.is_empty() => out.push_str("()"),
+            QueryExpr::Or(exprs) if exprs.is_empty() => out.push_str("(not ())"),
+            QueryExpr::And(exprs) => QueryExpr::write_sexp_list(out, "and", exprs),
+            QueryExpr::Or(exprs) => QueryExpr::write_sexp_list(out, "or", exprs),
+            QueryExpr::InThread(expr) => {
+                out.push_str("(thread (of ");
+                expr.write_sexp(out);
+                out.push_str("))");
+            }
+            QueryExpr::Sexp(sexp) => out.push_str(sexp),
+            // The S-expression syntax has no equivalent of these, so they are
+            // embedded in the infix syntax.
+            QueryExpr::Xor(_) | QueryExpr::Near(..) | QueryExpr::Adj(..) | QueryExpr::Custom(..) => {
+                out.push_str("(infix ");
+                out.push_str(&sexp_quote(&self.to_string()));
+                out.push(')');
+            }
+            QueryExpr::Not(expr) => {
+                out.push_str("(not ");
+                expr.write_sexp(out);
+                out.push(')');
+            }
+        }
+    }
+}
+
+impl ops::BitAnd for QueryExpr {
+    type Output = QueryExpr;
+
+    fn bitand(self, rhs: QueryExpr) -> QueryExpr {
+        match self {
+            QueryExpr::And(mut exprs) => {
+                exprs.push(rhs);
+                QueryExpr::And(exprs)
+            }
+            lhs => QueryExpr::And(vec![lhs, rhs]),
+        }
+    }
+}
+
+impl ops::BitOr for QueryExpr {
+    type Output = QueryExpr;
+
+    fn bitor(self, rhs: QueryExpr) -> QueryExpr {
+        match self {
+            QueryExpr::Or(mut exprs) => {
+                exprs.push(rhs);
+                QueryExpr::Or(exprs)
+            }
+            lhs => QueryExpr::Or(vec![lhs, rhs]),
+        }
+    }
+}
+
+impl ops::Not for QueryExpr {
+    type Output = QueryExpr;
+
+    fn not(self) -> QueryExpr {
+        QueryExpr::negate(self)
+    }
+}
+
+impl Query {
+    /// Create a query from a `QueryExpr`.
+    pub fn from_expr(database: &Database, expr: &QueryExpr) -> Result<Self> {
+        database.create_query(&expr.to_string())
+    }
+
+    /// Create a query from a `QueryExpr`, rendered for the given parser.
+    #[cfg(feature = "v0_34")]
+    pub fn from_expr_with_syntax(
+        database: &Database,
+        expr: &QueryExpr,
+        syntax: QuerySyntax,
+    ) -> Result<Self> {
+        let query_string = match syntax {
+            QuerySyntax::Xapian => expr.to_string(),
+            QuerySyntax::Sexp => expr.to_sexp(),
+        };
+        database.create_query_with_syntax(&query_string, syntax)
+    }
+}
@@ -0,0 +1,544 @@
+use std::error;
+use std::fmt;
+use std::str::FromStr;
+
+use error::Error;
+use ffi::Status;
+use Field;
+use QueryExpr;
+use RangeField;
+
+/// A syntax error in a notmuch query string.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct QueryParseError {
+    /// Byte offset into the query string.
+    pub offset: usize,
+    pub message: String,
+}
+
+impl fmt::Display for QueryParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} at offset {}", self.message, self.offset)
+    }
+}
+
+impl error::Error for QueryParseError {}
+
+impl From<QueryParseError> for Error {
+    fn from(err: QueryParseError) -> Error {
+        Error::NotmuchVerboseError(Status::BadQuerySyntax, err.to_string())
+    }
+}
+
+/// Something in a query that is valid syntax, but probably not what the
+/// user meant.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum QueryWarning {
+    /// `prefix:` is not a prefix known to notmuch, so `prefix:value` is
+    /// searched for as free text.
+    UnknownPrefix { offset: usize, prefix: String },
+}
+
+/// The result of `parse_query`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParsedQuery {
+    pub expr: QueryExpr,
+    pub warnings: Vec<QueryWarning>,
+}
+
+/// Parse a query in the notmuch infix syntax, without a round trip to
+/// Xapian.
+///
+/// The parser follows the notmuch flavour of the Xapian query parser:
+/// operators are case insensitive, juxtaposition means `and`, and `-term` /
+/// `+term` exclude / require a term. From loosest to tightest, the binary
+/// operators are `or`, `xor`, `and`, and `near` / `adj`.
+pub fn parse_query(query: &str) -> Result<ParsedQuery, QueryParseError> {
+    let mut parser = Parser {
+        input: query,
+        pos: 0,
+        warnings: vec![],
+    };
+
+    let expr = parser.parse_all()?;
+    Ok(ParsedQuery {
+        expr,
+        warnings: parser.warnings,
+    })
+}
+
+/// Parse and re-render a query in a canonical form: explicit lowercase
+/// operators, minimal quoting and `date:` / `lastmod:` values always written
+/// as `from..to` ranges. The normalized query means the same as `query`, so
+/// quoted phrases that look like prefixes or regexes stay quoted.
+pub fn normalize_query(query: &str) -> Result<String, QueryParseError> {
+    parse_query(query).map(|parsed| parsed.expr.to_string())
+}
+
+impl FromStr for QueryExpr {
+    type Err = QueryParseError;
+
+    fn from_str(s: &str) -> Result<Self, QueryParseError> {
+        parse_query(s).map(|parsed| parsed.expr)
+    }
+}
+
+struct Parser<'a> {
+    input: &'a str,
+    pos: usize,
+    warnings: Vec<QueryWarning>,
+}
+
+fn ends_word(c: char) -> bool {
+    c.is_whitespace() || c == '(' || c == ')' || c == '"'
+}
+
+/// Split a lowercase `near`, `adj`, `near/N` or `adj/N` operator into its
+/// name and distance.
+fn proximity_operator(op: &str) -> Option<(&str, Option<u32>)> {
+    let mut parts = op.splitn(2, '/');
+    let name = parts.next()?;
+    if name != "near" && name != "adj" {
+        return None;
+    }
+    match parts.next() {
+        Some(distance) => distance.parse().ok().map(|d| (name, Some(d))),
+        None => Some((name, None)),
+    }
+}
+
+/// Whether `expr` is a single term, as `near` and `adj` require.
+fn is_term(expr: &QueryExpr) -> bool {
+    match expr {
+        QueryExpr::Text(text) => !text.contains(char::is_whitespace),
+        QueryExpr::Field(..) | QueryExpr::Custom(..) => true,
+        _ => false,
+    }
+}
+
+impl<'a> Parser<'a> {
+    fn error(&self, message: &str) -> QueryParseError {
+        self.error_at(self.pos, message)
+    }
+
+    fn error_at(&self, offset: usize, message: &str) -> QueryParseError {
+        QueryParseError {
+            offset,
+            message: message.to_string(),
+        }
+    }
+
+    fn rest(&self) -> &'a str {
+        &self.input[self.pos..]
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.rest().chars().next()
+    }
+
+    fn at_end(&self) -> bool {
+        self.pos >= self.input.len()
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += c.len_utf8();
+        Some(c)
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().is_some_and(char::is_whitespace) {
+            self.bump();
+        }
+    }
+
+    /// The bare word at the current position, without consuming it.
+    fn peek_word(&self) -> &'a str {
+        let rest = self.rest();
+        let end = rest.find(ends_word).unwrap_or(rest.len());
+        &rest[..end]
+    }
+
+    /// The operator at the current position, if any.
+    fn peek_operator(&self) -> Option<String> {
+        let word = self.peek_word().to_ascii_lowercase();
+        match word.as_str() {
+            "and" | "or" | "not" | "xor" => Some(word),
+            _ if proximity_operator(&word).is_some() => Some(word),
+            _ => None,
+        }
+    }
+
+    /// Parse a whole query, which may be empty.
+    fn parse_all(&mut self) -> Result<QueryExpr, QueryParseError> {
+        self.skip_whitespace();
+        if self.at_end() {
+            return Ok(QueryExpr::All);
+        }
+
+        let expr = self.parse_or()?;
+        self.skip_whitespace();
+        if !self.at_end() {
+            return Err(self.error("unexpected ')'"));
+        }
+        Ok(expr)
+    }
+
+    fn parse_or(&mut self) -> Result<QueryExpr, QueryParseError> {
+        let mut exprs = vec![self.parse_xor()?];
+
+        loop {
+            self.skip_whitespace();
+            match self.peek_operator() {
+                Some(ref op) if op == "or" => {
+                    self.pos += op.len();
+                    exprs.push(self.parse_xor()?);
+                }
+                _ => break,
+            }
+        }
+
+        Ok(if exprs.len() == 1 {
+            exprs.pop().unwrap()
+        } else {
+            QueryExpr::Or(exprs)
+        })
+    }
+
+    fn parse_xor(&mut self) -> Result<QueryExpr, QueryParseError> {
+        let mut exprs = vec![self.parse_and()?];
+
+        loop {
+            self.skip_whitespace();
+            match self.peek_operator() {
+                Some(ref op) if op == "xor" => {
+                    self.pos += op.len();
+                    exprs.push(self.parse_and()?);
+                }
+                _ => break,
+            }
+        }
+
+        Ok(if exprs.len() == 1 {
+            exprs.pop().unwrap()
+        } else {
+            QueryExpr::Xor(exprs)
+        })
+    }
+
+    fn parse_and(&mut self) -> Result<QueryExpr, QueryParseError> {
+        let mut exprs = vec![self.parse_proximity()?];
+
+        loop {
+            self.skip_whitespace();
+            if self.at_end() || self.peek() == Some(')') {
+                break;
+            }
+
+            match self.peek_operator() {
+                Some(ref op) if op == "or" || op == "xor" => break,
+                Some(ref op) if op == "and" => {
+                    self.pos += op.len();
+                    exprs.push(self.parse_proximity()?);
+                }
+                _ => exprs.push(self.parse_proximity()?),
+            }
+        }
+
+        Ok(if exprs.len() == 1 {
+            exprs.pop().unwrap()
+        } else {
+            QueryExpr::And(exprs)
+        })
+    }
+
+    /// Parse terms joined by `near` or `adj`. A chain must use the same
+    /// operator and distance throughout, as in Xapian.
+    fn parse_proximity(&mut self) -> Result<QueryExpr, QueryParseError> {
+        self.skip_whitespace();
+        let mut starts = vec![self.pos];
+        let mut terms = vec![self.parse_unary()?];
+        let mut chain: Option<(String, Option<u32>)> = None;
+
+        loop {
+            self.skip_whitespace();
+            let op = match self.peek_operator() {
+                Some(op) => op,
+                None => break,
+            };
+            let (name, distance) = match proximity_operator(&op) {
+                Some(parsed) => parsed,
+                None => break,
+            };
+            if let Some((chain_name, chain_distance)) = &chain {
+                if chain_name != name || *chain_distance != distance {
+                    return Err(self.error("NEAR and ADJ with different distances can't be chained"));
+                }
+            }
+            chain = Some((name.to_string(), distance));
+
+            self.pos += op.len();
+            self.skip_whitespace();
+            starts.push(self.pos);
+            terms.push(self.parse_unary()?);
+        }
+
+        let (name, distance) = match chain {
+            Some(chain) => chain,
+            None => return Ok(terms.pop().unwrap()),
+        };
+        if let Some(i) = terms.iter().position(|term| !is_term(term)) {
+            return Err(self.error_at(starts[i], &format!("{} only combines single terms", name.to_uppercase())));
+        }
+
+        Ok(if name == "near" {
+            QueryExpr::Near(terms, distance)
+        } else {
+            QueryExpr::Adj(terms, distance)
+        })
+    }
+
+    fn parse_unary(&mut self) -> Result<QueryExpr, QueryParseError> {
+        self.skip_whitespace();
+
+        match self.peek_operator() {
+            Some(ref op) if op == "not" => {
+                self.pos += op.len();
+                return Ok(QueryExpr::negate(self.parse_unary()?));
+            }
+            Some(op) => {
+                return Err(self.error(&format!("unexpected operator '{}'", op.to_uppercase())))
+            }
+            None => {}
+        }
+
+        let mut chars = self.rest().chars();
+        match (chars.next(), chars.next()) {
+            (Some('-'), Some(c)) if !ends_word(c) => {
+                self.bump();
+                Ok(QueryExpr::negate(self.parse_primary()?))
+            }
+            (Some('+'), Some(c)) if !ends_word(c) => {
+                self.bump();
+                self.parse_primary()
+            }
+            _ => self.parse_primary(),
+        }
+    }
+
+    fn parse_primary(&mut self) -> Result<QueryExpr, QueryParseError> {
+        self.skip_whitespace();
+        let start = self.pos;
+
+        match self.peek() {
+            None => Err(self.error("expected a search term")),
+            Some(')') => Err(self.error("unexpected ')'")),
+            Some('(') => {
+                self.bump();
+                self.parse_group(start)
+            }
+            Some('"') => Ok(QueryExpr::Text(self.parse_phrase()?)),
+            Some(_) => {
+                let word = self.peek_word();
+                if word == "*" {
+                    self.pos += 1;
+                    return Ok(QueryExpr::All);
+                }
+
+                let prefix = word.find(':').map(|colon| &word[..colon]).filter(|prefix| {
+                    !prefix.is_empty() && prefix.chars().all(|c| c.is_ascii_alphabetic() || c == '_')
+                });
+
+                match prefix {
+                    Some(prefix) => {
+                        self.pos += prefix.len() + 1;
+                        self.parse_prefixed(start, prefix)
+                    }
+                    None => {
+                        self.pos += word.len();
+                        Ok(QueryExpr::Text(word.to_string()))
+                    }
+                }
+            }
+        }
+    }
+
+    /// Parse the rest of a parenthesized group, after the opening paren.
+    fn parse_group(&mut self, open: usize) -> Result<QueryExpr, QueryParseError> {
+        self.skip_whitespace();
+        if self.at_end() {
+            return Err(self.error_at(open, "unbalanced '('"));
+        }
+        if self.peek() == Some(')') {
+            return Err(self.error("empty parentheses"));
+        }
+
+        let expr = self.parse_or()?;
+        self.skip_whitespace();
+        match self.bump() {
+            Some(')') => Ok(expr),
+            _ => Err(self.error_at(open, "unbalanced '('")),
+        }
+    }
+
+    /// Parse a quoted phrase; quotes within it are doubled.
+    fn parse_phrase(&mut self) -> Result<String, QueryParseError> {
+        let open = self.pos;
+        self.bump();
+
+        let mut phrase = String::new();
+        loop {
+            match self.bump() {
+                None => return Err(self.error_at(open, "unterminated quoted string")),
+                Some('"') => {
+                    if self.peek() == Some('"') {
+                        self.bump();
+                        phrase.push('"');
+                    } else {
+                        return Ok(phrase);
+                    }
+                }
+                Some(c) => phrase.push(c),
+            }
+        }
+    }
+
+    /// Parse the value following `prefix:`, made of bare and quoted parts.
+    fn parse_value(&mut self) -> Result<String, QueryParseError> {
+        let mut value = String::new();
+        loop {
+            match self.peek() {
+                None | Some(')') => break,
+                Some(c) if c.is_whitespace() => break,
+                Some('"') => value.push_str(&self.parse_phrase()?),
+                Some(c) => {
+                    self.bump();
+                    value.push(c);
+                }
+            }
+        }
+        Ok(value)
+    }
+
+    /// Parse the rest of a `thread:{...}` subquery, after the opening
+    /// brace.
+    fn parse_subquery(&mut self, open: usize) -> Result<QueryExpr, QueryParseError> {
+        let start = self.pos;
+        let len = match self.rest().find('}') {
+            Some(len) => len,
+            None => return Err(self.error_at(open, "unbalanced '{'")),
+        };
+
+        let mut parser = Parser {
+            input: &self.input[..start + len],
+            pos: start,
+            warnings: vec![],
+        };
+        let expr = parser.parse_all()?;
+        self.warnings.extend(parser.warnings);
+
+        self.pos = start + len + 1;
+        Ok(expr)
+    }
+
+    fn parse_prefixed(&mut self, start: usize, prefix: &str) -> Result<QueryExpr, QueryParseError> {
+        if let Some(field) = RangeField::from_prefix(prefix) {
+            let value_start = self.pos;
+            let value = self.parse_value()?;
+            return parse_range(field, &value).map_err(|msg| self.error_at(value_start, &msg));
+        }
+
+        if prefix == "sexp" {
+            let value_start = self.pos;
+            let value = self.parse_value()?;
+            if value.is_empty() {
+                return Err(self.error_at(value_start, "missing value after 'sexp:'"));
+            }
+            return Ok(QueryExpr::Sexp(value));
+        }
+
+        let field = match Field::from_prefix(prefix) {
+            Some(field) => field,
+            None => {
+                self.warnings.push(QueryWarning::UnknownPrefix {
+                    offset: start,
+                    prefix: prefix.to_string(),
+                });
+                let value = self.parse_value()?;
+                return Ok(QueryExpr::Custom(prefix.to_string(), value));
+            }
+        };
+
+        if field == Field::Thread && self.peek() == Some('{') {
+            let open = self.pos;
+            self.bump();
+            let expr = self.parse_subquery(open)?;
+            return Ok(QueryExpr::in_thread(expr));
+        }
+
+        if self.peek() == Some('(') {
+            let open = self.pos;
+            self.bump();
+            let group = self.parse_group(open)?;
+            return apply_field(field, group).map_err(|msg| self.error_at(open, &msg));
+        }
+
+        let value_start = self.pos;
+        let value = self.parse_value()?;
+        if value.is_empty() && self.input[value_start..self.pos].is_empty() {
+            return Err(self.error_at(value_start, &format!("missing value after '{}:'", prefix)));
+        }
+
+        Ok(QueryExpr::Field(field, value))
+    }
+}
+
+/// Split a range value into its ends. A single value `x` and the notmuch
+/// shorthand `x..!` both mean `x..x`.
+fn parse_range(field: RangeField, value: &str) -> Result<QueryExpr, String> {
+    let (from, to) = match value.find("..") {
+        Some(pos) => {
+            let to = &value[pos + 2..];
+            let from = &value[..pos];
+            (from, if to == "!" { from } else { to })
+        }
+        None => (value, value),
+    };
+
+    if value.is_empty() {
+        return Err(format!("missing value after '{}:'", field.prefix()));
+    }
+
+    if field == RangeField::Lastmod {
+        for end in &[from, to] {
+            if !end.is_empty() && end.parse::<u64>().is_err() {
+                return Err("lastmod: expects revision numbers".to_string());
+            }
+        }
+    }
+
+    let end = |s: &str| if s.is_empty() { None } else { Some(s.to_string()) };
+    Ok(QueryExpr::Range(field, end(from), end(to)))
+}
+
+/// Distribute `field` over the free text terms of a group, as in
+/// `subject:(foo or bar)`.
+fn apply_field(field: Field, expr: QueryExpr) -> Result<QueryExpr, String> {
+    let apply_all = |exprs: Vec<QueryExpr>| {
+        exprs
+            .into_iter()
+            .map(|e| apply_field(field, e))
+            .collect::<Result<Vec<_>, _>>()
+    };
+
+    match expr {
+        QueryExpr::Text(text) => Ok(QueryExpr::Field(field, text)),
+        QueryExpr::And(exprs) => Ok(QueryExpr::And(apply_all(exprs)?)),
+        QueryExpr::Or(exprs) => Ok(QueryExpr::Or(apply_all(exprs)?)),
+        QueryExpr::Xor(exprs) => Ok(QueryExpr::Xor(apply_all(exprs)?)),
+        QueryExpr::Near(terms, distance) => Ok(QueryExpr::Near(apply_all(terms)?, distance)),
+        QueryExpr::Adj(terms, distance) => Ok(QueryExpr::Adj(apply_all(terms)?, distance)),
+        QueryExpr::Not(expr) => Ok(QueryExpr::negate(apply_field(field, *expr)?)),
+        _ => Err(format!("only plain terms can be grouped after '{}:'", field.prefix())),
+    }
+}
@@ -0,0 +1,203 @@
+use error::Result;
+use ffi::ConfigKey;
+use headers::decode_header;
+use Address;
+use BodyOptions;
+use Message;
+use MessageId;
+use MimePart;
+
+/// Who `Message::reply_template` addresses the reply to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ReplyTo {
+    /// Only the sender, or the `Reply-To` addresses.
+    Sender,
+    /// The sender and all other recipients, or the `Mail-Followup-To`
+    /// addresses.
+    All,
+}
+
+/// A draft reply, as `notmuch reply` would produce it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ReplyTemplate {
+    /// The address of the user the original message was sent to, or else
+    /// their primary address.
+    pub from: Option<Address>,
+    pub to: Vec<Address>,
+    pub cc: Vec<Address>,
+    /// The blind copy recipients of the original message, which, like
+    /// `notmuch reply`, stay blind copies.
+    pub bcc: Vec<Address>,
+    pub subject: String,
+    pub in_reply_to: Option<MessageId>,
+    pub references: Vec<MessageId>,
+    /// The text of the original message, quoted with `> ` and introduced by
+    /// an attribution line.
+    pub quoted_body: String,
+}
+
+fn is_user(address: &Address, user: &[Address]) -> bool {
+    user.iter().any(|u| u.address.eq_ignore_ascii_case(&address.address))
+}
+
+/// Add `addresses` to `list`, skipping the user's addresses and those
+/// already in `seen`. Returns how many were added.
+fn add_recipients(list: &mut Vec<Address>, seen: &mut Vec<String>, addresses: Vec<Address>, user: &[Address]) -> usize {
+    let mut added = 0;
+    for address in addresses {
+        let key = address.address.to_lowercase();
+        if is_user(&address, user) || seen.contains(&key) {
+            continue;
+        }
+        seen.push(key);
+        list.push(address);
+        added += 1;
+    }
+    added
+}
+
+fn quote(text: &str) -> String {
+    let mut out = String::with_capacity(text.len() + text.len() / 8);
+    for line in text.lines() {
+        if line.is_empty() {
+            out.push_str(">\n");
+        } else {
+            out.push_str("> ");
+            out.push_str(line);
+            out.push('\n');
+        }
+    }
+    out
+}
+
+impl MimePart {
+    /// A reply to the message this is the root part of, see
+    /// `Message::reply_template`. `user` holds the addresses of the user,
+    /// the first being their primary one.
+    pub fn reply_template(&self, reply_to: ReplyTo, user: &[Address]) -> ReplyTemplate {
+        let message_id = self
+            .header("message-id")
+            .and_then(|id| MessageId::parse_list(id).into_iter().next());
+        build_reply(self, message_id, reply_to, user)
+    }
+}
+
+fn build_reply(part: &MimePart, message_id: Option<MessageId>, reply_to: ReplyTo, user: &[Address]) -> ReplyTemplate {
+    let addresses = |name: &str| part.header(name).map(Address::parse_list).unwrap_or_default();
+
+    let from = addresses("from");
+    let to = addresses("to");
+    let cc = addresses("cc");
+    let bcc = addresses("bcc");
+
+    // A `Reply-To` that just repeats `To` was added by a mailing list, and
+    // the sender should still get the reply.
+    let mut reply_to_header = addresses("reply-to");
+    if reply_to_header.len() == 1 && to.len() == 1 && reply_to_header[0].address.eq_ignore_ascii_case(&to[0].address) {
+        reply_to_header.clear();
+    }
+    let sender = if reply_to_header.is_empty() { from } else { reply_to_header };
+
+    let mut reply = ReplyTemplate {
+        from: None,
+        to: vec![],
+        cc: vec![],
+        bcc: vec![],
+        subject: String::new(),
+        in_reply_to: None,
+        references: vec![],
+        quoted_body: String::new(),
+    };
+    let mut seen = vec![];
+
+    let followup = addresses("mail-followup-to");
+    match reply_to {
+        ReplyTo::All if !followup.is_empty() => {
+            add_recipients(&mut reply.to, &mut seen, followup, user);
+        }
+        ReplyTo::All => {
+            add_recipients(&mut reply.to, &mut seen, sender, user);
+            add_recipients(&mut reply.to, &mut seen, to.clone(), user);
+            add_recipients(&mut reply.cc, &mut seen, cc.clone(), user);
+            add_recipients(&mut reply.bcc, &mut seen, bcc.clone(), user);
+        }
+        ReplyTo::Sender => {
+            // Replying to one's own message goes to its recipients.
+            if add_recipients(&mut reply.to, &mut seen, sender, user) == 0 {
+                add_recipients(&mut reply.to, &mut seen, to.clone(), user);
+            }
+        }
+    }
+
+    reply.from = to
+        .iter()
+        .chain(&cc)
+        .chain(&bcc)
+        .find_map(|address| user.iter().find(|u| u.address.eq_ignore_ascii_case(&address.address)))
+        .or_else(|| user.first())
+        .cloned();
+
+    let subject = part.header("subject").map(decode_header).unwrap_or_default();
+    reply.subject = if subject.get(..3).is_some_and(|prefix| prefix.eq_ignore_ascii_case("re:")) {
+        subject
+    } else {
+        format!("Re: {}", subject)
+    };
+
+    reply.references = match part.header("references") {
+        Some(references) => MessageId::parse_list(references),
+        None => part
+            .header("in-reply-to")
+            .map(MessageId::parse_list)
+            .unwrap_or_default()
+            .into_iter()
+            .take(1)
+            .collect(),
+    };
+    if let Some(id) = &message_id {
+        if reply.references.last() != Some(id) {
+            reply.references.push(id.clone());
+        }
+    }
+    reply.in_reply_to = message_id;
+
+    let author = part.header("from").map(decode_header).unwrap_or_default();
+    reply.quoted_body = match part.header("date") {
+        Some(date) => format!("On {}, {} wrote:\n", date, author),
+        None => format!("{} wrote:\n", author),
+    };
+    if let Some(text) = part.body_text(&BodyOptions::default()) {
+        reply.quoted_body.push_str(&quote(&text));
+    }
+
+    reply
+}
+
+impl Message {
+    /// A draft reply to this message, like `notmuch reply` makes.
+    ///
+    /// The user's own addresses, from `user.primary_email` and
+    /// `user.other_email`, are left out of the recipients. `Reply-To` is
+    /// honoured, except when a mailing list set it to its own address, and
+    /// `Mail-Followup-To` takes precedence when replying to all.
+    pub fn reply_template(&self, reply_to: ReplyTo) -> Result<ReplyTemplate> {
+        let database = self.database();
+        let name = database.config(ConfigKey::UserName).filter(|name| !name.is_empty());
+
+        let mut user = vec![];
+        if let Some(primary) = database.config(ConfigKey::PrimaryEmail) {
+            user.push(primary);
+        }
+        if let Some(other) = database.config_values(ConfigKey::OtherEmail) {
+            user.extend(other);
+        }
+        let user: Vec<Address> = user
+            .iter()
+            .filter(|address| !address.is_empty())
+            .map(|address| Address::new(name.as_deref(), address))
+            .collect();
+
+        let message_id = Some(MessageId(self.id().into_owned()));
+        Ok(build_reply(&self.parts()?, message_id, reply_to, &user))
+    }
+}
@@ -0,0 +1,410 @@
+use std::collections::{BTreeSet, HashMap};
+use std::fs;
+use std::mem;
+use std::os::unix::fs::MetadataExt;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use regex::Regex;
+
+use error::{Error, Result};
+use ffi::{ConfigKey, Status};
+use AtomicOperation;
+use Database;
+use Directory;
+use FrozenMessage;
+
+/// Outcome of `Database::scan_new`.
+///
+/// Files that were recognised as renamed are only listed in `renamed`, not
+/// in `added` or `removed`.
+#[derive(Debug, Default)]
+pub struct ScanReport {
+    /// Files that were added to the database.
+    pub added: Vec<PathBuf>,
+    /// Files that disappeared from disk and were removed from the database.
+    pub removed: Vec<PathBuf>,
+    /// Files that moved, as `(from, to)` pairs.
+    pub renamed: Vec<(PathBuf, PathBuf)>,
+    /// Files that could not be indexed or removed.
+    pub failed: Vec<(PathBuf, Error)>,
+}
+
+enum IgnorePattern {
+    /// Matches a file or directory basename exactly.
+    Name(String),
+    /// Matches the path relative to the mail root, written as `/regex/`.
+    Regex(Regex),
+}
+
+impl IgnorePattern {
+    fn parse(pattern: &str) -> Result<Self> {
+        if pattern.len() > 1 && pattern.starts_with('/') && pattern.ends_with('/') {
+            Regex::new(&pattern[1..pattern.len() - 1])
+                .map(IgnorePattern::Regex)
+                .map_err(|_| Error::NotmuchVerboseError(
+                    Status::IllegalArgument,
+                    format!("invalid new.ignore regex {}", pattern),
+                ))
+        } else {
+            Ok(IgnorePattern::Name(pattern.to_string()))
+        }
+    }
+
+    fn matches(&self, name: &str, relative: &str) -> bool {
+        match self {
+            IgnorePattern::Name(n) => n == name,
+            IgnorePattern::Regex(re) => re.is_match(relative),
+        }
+    }
+}
+
+pub(crate) struct Scanner<'d> {
+    database: &'d Database,
+    root: PathBuf,
+    new_tags: Vec<String>,
+    ignore: Vec<IgnorePattern>,
+    synchronize_flags: bool,
+    now: i64,
+    /// Files found on disk but not in the database.
+    new_files: Vec<PathBuf>,
+    /// Files in the database that are gone from disk.
+    vanished_files: Vec<PathBuf>,
+    /// Directories that are gone from disk, children first.
+    vanished_dirs: Vec<(PathBuf, Directory)>,
+    /// Directory mtimes to store once all changes are applied.
+    mtimes: Vec<(PathBuf, i64)>,
+    report: ScanReport,
+}
+
+impl<'d> Scanner<'d> {
+    pub(crate) fn new(database: &'d Database) -> Result<Self> {
+        let root = database
+            .config(ConfigKey::MailRoot)
+            .map(PathBuf::from)
+            .unwrap_or_else(|| database.path().to_path_buf());
+
+        let new_tags = database
+            .config_values(ConfigKey::NewTags)
+            .map(|values| values.filter(|t| !t.is_empty()).collect())
+            .unwrap_or_default();
+
+        let ignore = database
+            .config_values(ConfigKey::NewIgnore)
+            .map(|values| {
+                values
+                    .filter(|p| !p.is_empty())
+                    .map(|p| IgnorePattern::parse(&p))
+                    .collect::<Result<Vec<_>>>()
+            })
+            .unwrap_or_else(|| Ok(vec![]))?;
+
+        let synchronize_flags = database.config_bool(ConfigKey::MaildirFlags)?;
+
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs() as i64)
+            .unwrap_or(0);
+
+        Ok(Scanner {
+            database,
+            root,
+            new_tags,
+            ignore,
+            synchronize_flags,
+            now,
+            new_files: vec![],
+            vanished_files: vec![],
+            vanished_dirs: vec![],
+            mtimes: vec![],
+            report: ScanReport::default(),
+        })
+    }
+
+    pub(crate) fn root(&self) -> &Path {
+        &self.root
+    }
+
+    pub(crate) fn is_ignored(&self, path: &Path) -> bool {
+        let name = match path.file_name().and_then(|n| n.to_str()) {
+            Some(name) => name,
+            None => return true,
+        };
+
+        if name == ".notmuch" {
+            return true;
+        }
+
+        let relative = path
+            .strip_prefix(&self.root)
+            .ok()
+            .and_then(|p| p.to_str())
+            .unwrap_or(name);
+
+        self.ignore.iter().any(|p| p.matches(name, relative))
+    }
+
+    /// The names of the files and subdirectories of `path` that are to be
+    /// indexed.
+    pub(crate) fn list_directory(&self, path: &Path) -> Result<(BTreeSet<PathBuf>, BTreeSet<PathBuf>)> {
+        let mut files = BTreeSet::new();
+        let mut subdirs = BTreeSet::new();
+        for entry in fs::read_dir(path)? {
+            let entry_path = entry?.path();
+            if self.is_ignored(&entry_path) {
+                continue;
+            }
+
+            // follow symlinks, like `notmuch new` does
+            let metadata = match fs::metadata(&entry_path) {
+                Ok(metadata) => metadata,
+                Err(_) => continue,
+            };
+
+            let name = PathBuf::from(entry_path.file_name().unwrap());
+            if metadata.is_dir() {
+                subdirs.insert(name);
+            } else if metadata.is_file() {
+                files.insert(name);
+            }
+        }
+
+        // The tmp folder of a maildir holds messages that are still being
+        // delivered.
+        if subdirs.contains(Path::new("cur")) || subdirs.contains(Path::new("new")) {
+            subdirs.remove(Path::new("tmp"));
+        }
+
+        Ok((files, subdirs))
+    }
+
+    /// Walk the directory tree and collect the differences with the
+    /// database, without changing the latter.
+    pub(crate) fn scan_directory(&mut self, path: &Path) -> Result<()> {
+        let fs_mtime = fs::metadata(path)?.mtime();
+        let (files, subdirs) = self.list_directory(path)?;
+
+        for subdir in &subdirs {
+            self.scan_directory(&path.join(subdir))?;
+        }
+
+        // A directory without a record is new to the database, or held no
+        // mail so far. Like `notmuch new`, treat it as empty with mtime 0.
+        let (db_mtime, db_files, db_subdirs) = match self.database.directory(path)? {
+            Some(directory) => (
+                directory.mtime(),
+                directory.child_files().collect(),
+                directory.child_directories().collect(),
+            ),
+            None => (0, BTreeSet::new(), BTreeSet::new()),
+        };
+
+        // Adding or removing an entry updates the mtime of the directory, so
+        // an unchanged mtime means there is nothing to do here.
+        if db_mtime == fs_mtime {
+            return Ok(());
+        }
+
+        self.new_files
+            .extend(files.difference(&db_files).map(|name| path.join(name)));
+        self.vanished_files
+            .extend(db_files.difference(&files).map(|name| path.join(name)));
+
+        for name in db_subdirs.difference(&subdirs) {
+            self.scan_vanished_directory(&path.join(name))?;
+        }
+
+        // Files delivered later within the same second would not change the
+        // mtime, so only remember it once that second has passed.
+        if fs_mtime < self.now {
+            self.mtimes.push((path.to_path_buf(), fs_mtime));
+        }
+
+        Ok(())
+    }
+
+    /// Queue a file found on disk to be indexed, unless it already is.
+    #[cfg(feature = "watch")]
+    pub(crate) fn file_added(&mut self, path: &Path) -> Result<()> {
+        if !self.new_files.iter().any(|p| p == path)
+            && self.database.find_message_by_filename(&path)?.is_none()
+        {
+            self.new_files.push(path.to_path_buf());
+        }
+        Ok(())
+    }
+
+    /// Queue a file that is gone from disk to be removed.
+    #[cfg(feature = "watch")]
+    pub(crate) fn file_removed(&mut self, path: &Path) {
+        self.new_files.retain(|p| p != path);
+        if !self.vanished_files.iter().any(|p| p == path) {
+            self.vanished_files.push(path.to_path_buf());
+        }
+    }
+
+    /// Queue everything below a directory that is gone from disk to be
+    /// removed.
+    pub(crate) fn scan_vanished_directory(&mut self, path: &Path) -> Result<()> {
+        let directory = match self.database.directory(path)? {
+            Some(directory) => directory,
+            None => return Ok(()),
+        };
+
+        self.vanished_files
+            .extend(directory.child_files().map(|name| path.join(name)));
+
+        let subdirs: Vec<PathBuf> = directory.child_directories().collect();
+        for name in subdirs {
+            self.scan_vanished_directory(&path.join(name))?;
+        }
+
+        self.vanished_dirs.push((path.to_path_buf(), directory));
+        Ok(())
+    }
+
+    /// Apply the collected differences to the database.
+    ///
+    /// New files are indexed before vanished ones are removed. A new file
+    /// that turns out to belong to the same message as a vanished one is
+    /// treated as a rename: both happen within one atomic operation, so the
+    /// message never drops out of the database and keeps its tags and
+    /// properties.
+    ///
+    /// Failures are collected in the report and don't stop the other
+    /// changes from being applied.
+    pub(crate) fn apply(&mut self) {
+        let mut vanished: HashMap<String, Vec<PathBuf>> = HashMap::new();
+        let mut orphans = vec![];
+        for path in mem::take(&mut self.vanished_files) {
+            match self.database.find_message_by_filename(&path) {
+                Ok(Some(message)) => vanished
+                    .entry(message.id().into_owned())
+                    .or_default()
+                    .push(path),
+                Ok(None) => orphans.push(path),
+                Err(err) => self.report.failed.push((path, err)),
+            }
+        }
+
+        for path in mem::take(&mut self.new_files) {
+            if let Err(err) = self.add_file(&path, &mut vanished) {
+                self.report.failed.push((path, err));
+            }
+        }
+
+        let mut remaining: Vec<PathBuf> = vanished.into_values().flatten().collect();
+        remaining.extend(orphans);
+        remaining.sort();
+        for path in remaining {
+            if let Err(err) = self.remove_file(&path) {
+                self.report.failed.push((path, err));
+            }
+        }
+
+        for (path, directory) in mem::take(&mut self.vanished_dirs) {
+            if let Err(err) = directory.delete() {
+                self.report.failed.push((path, err));
+            }
+        }
+
+        // Indexing the first file of a directory creates its record, so it
+        // is only looked up now.
+        for (path, mtime) in mem::take(&mut self.mtimes) {
+            let result = self
+                .database
+                .directory(&path)
+                .and_then(|directory| directory.map_or(Ok(()), |d| d.set_mtime(mtime)));
+            if let Err(err) = result {
+                self.report.failed.push((path, err));
+            }
+        }
+    }
+
+    /// The changes applied so far, leaving an empty report behind.
+    pub(crate) fn take_report(&mut self) -> ScanReport {
+        mem::take(&mut self.report)
+    }
+
+    fn add_file(&mut self, path: &Path, vanished: &mut HashMap<String, Vec<PathBuf>>) -> Result<()> {
+        let _atomic = AtomicOperation::new(self.database)?;
+
+        let (message, duplicate) = self.database.index_file_with_status(path, None)?;
+
+        if !duplicate {
+            let _frozen = FrozenMessage::new(&message)?;
+            for tag in &self.new_tags {
+                message.add_tag(tag)?;
+            }
+            if self.synchronize_flags {
+                message.maildir_flags_to_tags()?;
+            }
+            self.report.added.push(path.to_path_buf());
+            return Ok(());
+        }
+
+        let renamed_from = vanished
+            .get_mut(&*message.id())
+            .and_then(|paths| paths.pop());
+
+        match renamed_from {
+            Some(from) => {
+                // The message still has the new filename, so it persists
+                // with all of its tags and properties.
+                self.database.remove_message(&from)?;
+                self.report.renamed.push((from, path.to_path_buf()));
+            }
+            None => self.report.added.push(path.to_path_buf()),
+        }
+
+        if self.synchronize_flags {
+            message.maildir_flags_to_tags()?;
+        }
+
+        Ok(())
+    }
+
+    fn remove_file(&mut self, path: &Path) -> Result<()> {
+        let message = self.database.find_message_by_filename(&path)?;
+
+        self.database.remove_message(path)?;
+        self.report.removed.push(path.to_path_buf());
+
+        if !self.synchronize_flags {
+            return Ok(());
+        }
+
+        // The remaining filenames of the message may carry other flags.
+        match message {
+            Some(message) => match self.database.find_message(&message.id())? {
+                Some(message) => message.maildir_flags_to_tags(),
+                None => Ok(()),
+            },
+            None => Ok(()),
+        }
+    }
+}
+
+impl Database {
+    /// Incrementally synchronize the database with the mail root, like
+    /// `notmuch new` does.
+    ///
+    /// Directories whose mtime matches the one stored in the database are
+    /// skipped. New files are indexed and tagged with `new.tags`, vanished
+    /// files are removed. Entries matching `new.ignore` are skipped and
+    /// maildir flags are synchronized to tags if `maildir.synchronize_flags`
+    /// is set.
+    ///
+    /// Files that moved, e.g. from `new/` to `cur/` or between folders, are
+    /// detected by their message id and keep their tags and properties.
+    ///
+    /// Failures to index or remove a single file or directory do not abort
+    /// the scan; they are collected in `ScanReport::failed`.
+    pub fn scan_new(&self) -> Result<ScanReport> {
+        let mut scanner = Scanner::new(self)?;
+        let root = scanner.root().to_path_buf();
+        scanner.scan_directory(&root)?;
+        scanner.apply();
+        Ok(scanner.take_report())
+    }
+}
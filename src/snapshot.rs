@@ -0,0 +1,170 @@
+use std::collections::btree_map;
+use std::collections::BTreeMap;
+use std::iter::FromIterator;
+use std::path::PathBuf;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use error::Result;
+use ffi::MessageFlag;
+use Message;
+use Thread;
+
+/// The headers `MessageSummary` captures.
+const SUMMARY_HEADERS: &[&str] = &[
+    "from",
+    "to",
+    "cc",
+    "bcc",
+    "reply-to",
+    "subject",
+    "date",
+    "message-id",
+    "in-reply-to",
+    "references",
+];
+
+/// The properties of a message, each key mapping to all of its values in
+/// the order notmuch returned them.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(transparent))]
+pub struct PropertyMap(BTreeMap<String, Vec<String>>);
+
+impl PropertyMap {
+    pub fn new() -> Self {
+        PropertyMap(BTreeMap::new())
+    }
+
+    pub fn insert(&mut self, key: String, value: String) {
+        self.0.entry(key).or_default().push(value);
+    }
+
+    /// All values of `key`, empty if it has none.
+    pub fn get(&self, key: &str) -> &[String] {
+        self.0.get(key).map_or(&[], Vec::as_slice)
+    }
+
+    pub fn contains_key(&self, key: &str) -> bool {
+        self.0.contains_key(key)
+    }
+
+    pub fn keys(&self) -> btree_map::Keys<'_, String, Vec<String>> {
+        self.0.keys()
+    }
+
+    pub fn iter(&self) -> btree_map::Iter<'_, String, Vec<String>> {
+        self.0.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl FromIterator<(String, String)> for PropertyMap {
+    fn from_iter<I: IntoIterator<Item = (String, String)>>(iter: I) -> Self {
+        let mut map = PropertyMap::new();
+        for (key, value) in iter {
+            map.insert(key, value);
+        }
+        map
+    }
+}
+
+/// An owned copy of a message, which unlike `Message` can outlive the
+/// database, be cached or be sent to other threads. With the `serde`
+/// feature, it can be serialized.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct MessageSummary {
+    pub id: String,
+    pub thread_id: String,
+    pub filenames: Vec<PathBuf>,
+    pub date: i64,
+    /// Common headers by lowercase name, raw as in the message. Headers the
+    /// message doesn't have are left out.
+    pub headers: BTreeMap<String, String>,
+    pub tags: Vec<String>,
+    pub properties: PropertyMap,
+    /// Whether the message matched the query it was found with.
+    pub matched: bool,
+    pub excluded: bool,
+}
+
+/// An owned copy of a thread and its messages, see `MessageSummary`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ThreadSummary {
+    pub id: String,
+    pub subject: String,
+    pub authors: Vec<String>,
+    pub oldest_date: i64,
+    pub newest_date: i64,
+    pub total_messages: i32,
+    pub matched_messages: i32,
+    pub tags: Vec<String>,
+    /// All messages of the thread, oldest first.
+    pub messages: Vec<MessageSummary>,
+}
+
+/// Split the authors of a thread the way notmuch joins them: with `, `, and
+/// with `| ` between the authors of matched messages and the others. Names
+/// may contain commas themselves.
+fn split_authors(authors: &str) -> Vec<String> {
+    authors
+        .split("| ")
+        .flat_map(|group| group.split(", "))
+        .filter(|author| !author.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+impl Message {
+    /// Copy the data of the message, so that it can be used after the
+    /// database is gone.
+    pub fn snapshot(&self) -> Result<MessageSummary> {
+        let mut headers = BTreeMap::new();
+        for &name in SUMMARY_HEADERS {
+            if let Some(value) = self.header(name)? {
+                if !value.is_empty() {
+                    headers.insert(name.to_string(), value.into_owned());
+                }
+            }
+        }
+
+        Ok(MessageSummary {
+            id: self.id().into_owned(),
+            thread_id: self.thread_id().into_owned(),
+            filenames: self.filenames().collect(),
+            date: self.date(),
+            headers,
+            tags: self.tags().collect(),
+            properties: self.properties("", false).collect(),
+            matched: self.get_flag(MessageFlag::Match),
+            excluded: self.get_flag(MessageFlag::Excluded),
+        })
+    }
+}
+
+impl Thread {
+    /// Copy the thread and all of its messages, see `Message::snapshot`.
+    pub fn snapshot(&self) -> Result<ThreadSummary> {
+        Ok(ThreadSummary {
+            id: self.id().to_string(),
+            subject: self.subject().into_owned(),
+            // `Thread::authors` splits at every comma, so join them back.
+            authors: split_authors(&self.authors().join(",")),
+            oldest_date: self.oldest_date(),
+            newest_date: self.newest_date(),
+            total_messages: self.total_messages(),
+            matched_messages: self.matched_messages(),
+            tags: self.tags().collect(),
+            messages: self.messages().map(|m| m.snapshot()).collect::<Result<_>>()?,
+        })
+    }
+}
@@ -0,0 +1,161 @@
+use std::io::{self, Write};
+
+/// The structure of a document, independent of the output format. Like
+/// notmuch's structure printers, the formats write the same values, so
+/// they carry the same fields.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum Value {
+    Null,
+    Bool(bool),
+    Int(i64),
+    String(String),
+    List(Vec<Value>),
+    Map(Vec<(&'static str, Value)>),
+}
+
+impl Value {
+    pub(crate) fn string<S: Into<String>>(s: S) -> Value {
+        Value::String(s.into())
+    }
+
+    pub(crate) fn strings<I, S>(strings: I) -> Value
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        Value::List(strings.into_iter().map(Value::string).collect())
+    }
+}
+
+fn write_json_string<W: Write>(writer: &mut W, s: &str) -> io::Result<()> {
+    writer.write_all(b"\"")?;
+    for c in s.chars() {
+        match c {
+            '"' => writer.write_all(b"\\\"")?,
+            '\\' => writer.write_all(b"\\\\")?,
+            '\n' => writer.write_all(b"\\n")?,
+            '\r' => writer.write_all(b"\\r")?,
+            '\t' => writer.write_all(b"\\t")?,
+            '\u{8}' => writer.write_all(b"\\b")?,
+            '\u{c}' => writer.write_all(b"\\f")?,
+            c if (c as u32) < 0x20 => write!(writer, "\\u{:04x}", c as u32)?,
+            c => write!(writer, "{}", c)?,
+        }
+    }
+    writer.write_all(b"\"")
+}
+
+/// Write `value` as JSON, spacing it like `notmuch --format=json` does.
+/// The items of a toplevel list go on lines of their own.
+pub(crate) fn write_json<W: Write>(writer: &mut W, value: &Value) -> io::Result<()> {
+    write_json_value(writer, value, true)?;
+    writer.write_all(b"\n")
+}
+
+fn write_json_value<W: Write>(writer: &mut W, value: &Value, toplevel: bool) -> io::Result<()> {
+    match value {
+        Value::Null => writer.write_all(b"null"),
+        Value::Bool(b) => write!(writer, "{}", b),
+        Value::Int(i) => write!(writer, "{}", i),
+        Value::String(s) => write_json_string(writer, s),
+        Value::List(items) => {
+            writer.write_all(b"[")?;
+            for (i, item) in items.iter().enumerate() {
+                if i > 0 {
+                    writer.write_all(if toplevel { b",\n" } else { b", " })?;
+                }
+                write_json_value(writer, item, false)?;
+            }
+            writer.write_all(b"]")
+        }
+        Value::Map(entries) => {
+            writer.write_all(b"{")?;
+            for (i, (key, item)) in entries.iter().enumerate() {
+                if i > 0 {
+                    writer.write_all(b", ")?;
+                }
+                write_json_string(writer, key)?;
+                writer.write_all(b": ")?;
+                write_json_value(writer, item, false)?;
+            }
+            writer.write_all(b"}")
+        }
+    }
+}
+
+fn write_sexp_string<W: Write>(writer: &mut W, s: &str) -> io::Result<()> {
+    writer.write_all(b"\"")?;
+    for c in s.chars() {
+        match c {
+            '"' => writer.write_all(b"\\\"")?,
+            '\\' => writer.write_all(b"\\\\")?,
+            '\n' => writer.write_all(b"\\n")?,
+            // Octal escapes work in most Schemes and in Emacs.
+            c if (c as u32) < 0x20 => write!(writer, "\\{:03o}", c as u32)?,
+            c => write!(writer, "{}", c)?,
+        }
+    }
+    writer.write_all(b"\"")
+}
+
+/// Write `value` as an S-expression like `notmuch --format=sexp`: lists
+/// become lists, maps become property lists with `:key` keywords, and
+/// `null` and `false` both become `nil`.
+pub(crate) fn write_sexp<W: Write>(writer: &mut W, value: &Value) -> io::Result<()> {
+    write_sexp_value(writer, value, true)?;
+    writer.write_all(b"\n")
+}
+
+fn write_sexp_value<W: Write>(writer: &mut W, value: &Value, toplevel: bool) -> io::Result<()> {
+    match value {
+        Value::Null | Value::Bool(false) => writer.write_all(b"nil"),
+        Value::Bool(true) => writer.write_all(b"t"),
+        Value::Int(i) => write!(writer, "{}", i),
+        Value::String(s) => write_sexp_string(writer, s),
+        Value::List(items) => {
+            writer.write_all(b"(")?;
+            for (i, item) in items.iter().enumerate() {
+                if i > 0 {
+                    writer.write_all(if toplevel { b"\n" } else { b" " })?;
+                }
+                write_sexp_value(writer, item, false)?;
+            }
+            writer.write_all(b")")
+        }
+        Value::Map(entries) => {
+            writer.write_all(b"(")?;
+            for (i, (key, item)) in entries.iter().enumerate() {
+                if i > 0 {
+                    writer.write_all(b" ")?;
+                }
+                write!(writer, ":{} ", key)?;
+                write_sexp_value(writer, item, false)?;
+            }
+            writer.write_all(b")")
+        }
+    }
+}
+
+/// Write `value` like `notmuch search --format=text0`: every item of a
+/// list is written on its own, terminated by a NUL byte.
+///
+/// text0 only has room for flat lists of scalars, so there is nothing to
+/// write maps with.
+pub(crate) fn write_text0<W: Write>(writer: &mut W, value: &Value) -> io::Result<()> {
+    match value {
+        Value::Null => writer.write_all(b"\0"),
+        Value::Bool(b) => write!(writer, "{}\0", b),
+        Value::Int(i) => write!(writer, "{}\0", i),
+        Value::String(s) => {
+            writer.write_all(s.as_bytes())?;
+            writer.write_all(b"\0")
+        }
+        Value::List(items) => {
+            for item in items {
+                write_text0(writer, item)?;
+            }
+            Ok(())
+        }
+        Value::Map(_) => unreachable!("text0 cannot represent maps"),
+    }
+}
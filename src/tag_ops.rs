@@ -0,0 +1,258 @@
+use std::fmt;
+use std::io::BufRead;
+use std::str::FromStr;
+
+use error::{Error, Result};
+#[cfg(feature = "v0_32")]
+use ffi::ConfigKey;
+use ffi::Status;
+use utils::{hex_decode, hex_encode};
+use AtomicOperation;
+use Database;
+use FrozenMessage;
+use Message;
+use Query;
+
+/// A single tag change.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TagOp {
+    Add(String),
+    Remove(String),
+}
+
+/// A list of tag changes, as in `notmuch tag +inbox -unread`.
+///
+/// The textual form uses the `%XX` escaping of `notmuch tag --batch`, so
+/// that tags containing spaces or other special characters survive a round
+/// trip. Plain tags are their own encoding. Removing all tags is written as
+/// `-*`, which can't be confused with a tag since `*` is always escaped.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct TagOps {
+    /// Remove all tags before applying `ops`.
+    pub remove_all: bool,
+    pub ops: Vec<TagOp>,
+}
+
+fn syntax_error(reason: &str) -> Error {
+    Error::NotmuchVerboseError(Status::IllegalArgument, reason.to_string())
+}
+
+/// Prefix the message of a syntax error with the line it was found on.
+pub(crate) fn at_line(line: usize, err: Error) -> Error {
+    match err {
+        Error::NotmuchVerboseError(status, reason) => {
+            Error::NotmuchVerboseError(status, format!("line {}: {}", line, reason))
+        }
+        err => err,
+    }
+}
+
+impl TagOps {
+    pub fn new() -> Self {
+        TagOps::default()
+    }
+
+    pub fn with_add(mut self, tag: &str) -> Self {
+        self.ops.push(TagOp::Add(tag.to_string()));
+        self
+    }
+
+    pub fn with_remove(mut self, tag: &str) -> Self {
+        self.ops.push(TagOp::Remove(tag.to_string()));
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        !self.remove_all && self.ops.is_empty()
+    }
+
+    /// Add the operation of a single word, where `-*` removes all tags.
+    fn push_word(&mut self, word: &str) -> Result<()> {
+        if word == "-*" {
+            self.remove_all = true;
+        } else {
+            self.ops.push(TagOps::parse_op(word)?);
+        }
+        Ok(())
+    }
+
+    /// Parse a single `+tag` or `-tag` word.
+    fn parse_op(word: &str) -> Result<TagOp> {
+        let (add, encoded) = match word.chars().next() {
+            Some('+') => (true, &word[1..]),
+            Some('-') => (false, &word[1..]),
+            _ => return Err(syntax_error("tag operations must start with '+' or '-'")),
+        };
+
+        let tag = hex_decode(encoded).ok_or_else(|| syntax_error("invalid hex encoding"))?;
+        if tag.is_empty() {
+            return Err(syntax_error("empty tag forbidden"));
+        }
+        if tag.starts_with('-') {
+            return Err(syntax_error("tag starting with '-' forbidden"));
+        }
+
+        Ok(if add { TagOp::Add(tag) } else { TagOp::Remove(tag) })
+    }
+
+    /// Parse a line of a `notmuch tag --batch` file into the tag changes and
+    /// the (decoded) query they apply to.
+    ///
+    /// The operations end at `--` or at the first word that doesn't start
+    /// with `+` or `-`. Returns `None` for blank lines and comments.
+    pub fn parse_batch_line(line: &str) -> Result<Option<(TagOps, String)>> {
+        TagOps::parse_line(line, false)
+    }
+
+    /// Parse a tag line of `notmuch dump`, which unlike a batch line must
+    /// separate the query with `--`.
+    pub(crate) fn parse_dump_line(line: &str) -> Result<Option<(TagOps, String)>> {
+        TagOps::parse_line(line, true)
+    }
+
+    fn parse_line(line: &str, require_separator: bool) -> Result<Option<(TagOps, String)>> {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            return Ok(None);
+        }
+
+        let mut ops = TagOps::new();
+        let mut rest = line;
+        let mut separated = false;
+        loop {
+            let word = rest.split_whitespace().next().unwrap_or("");
+            if word.is_empty() {
+                break;
+            }
+
+            if word == "--" {
+                rest = rest.trim_start()[2..].trim_start();
+                separated = true;
+                break;
+            }
+            if !word.starts_with('+') && !word.starts_with('-') {
+                break;
+            }
+
+            ops.push_word(word)?;
+            rest = rest.trim_start()[word.len()..].trim_start();
+        }
+
+        if require_separator && !separated {
+            return Err(syntax_error("missing '--' before the query"));
+        }
+
+        let query = hex_decode(rest.trim()).ok_or_else(|| syntax_error("invalid hex encoding"))?;
+        if query.is_empty() {
+            return Err(syntax_error("no query string"));
+        }
+
+        Ok(Some((ops, query)))
+    }
+}
+
+impl FromStr for TagOps {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mut ops = TagOps::new();
+        for word in s.split_whitespace() {
+            ops.push_word(word)?;
+        }
+        Ok(ops)
+    }
+}
+
+impl fmt::Display for TagOp {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TagOp::Add(tag) => write!(f, "+{}", hex_encode(tag)),
+            TagOp::Remove(tag) => write!(f, "-{}", hex_encode(tag)),
+        }
+    }
+}
+
+impl fmt::Display for TagOps {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut first = true;
+        if self.remove_all {
+            f.write_str("-*")?;
+            first = false;
+        }
+        for op in &self.ops {
+            if !first {
+                f.write_str(" ")?;
+            }
+            op.fmt(f)?;
+            first = false;
+        }
+        Ok(())
+    }
+}
+
+impl Message {
+    /// Apply tag changes to this message, with the message frozen so the
+    /// changes become visible at once.
+    ///
+    /// As with `notmuch tag`, the tags are synchronized to maildir flags
+    /// afterwards if `maildir.synchronize_flags` is set.
+    pub fn apply(&self, ops: &TagOps) -> Result<()> {
+        {
+            let _frozen = FrozenMessage::new(self)?;
+
+            if ops.remove_all {
+                self.remove_all_tags()?;
+            }
+            for op in &ops.ops {
+                match op {
+                    TagOp::Add(tag) => self.add_tag(tag)?,
+                    TagOp::Remove(tag) => self.remove_tag(tag)?,
+                }
+            }
+        }
+
+        #[cfg(feature = "v0_32")]
+        {
+            if self.database().config_bool(ConfigKey::MaildirFlags)? {
+                self.tags_to_maildir_flags()?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Query {
+    /// Apply tag changes to all messages matching this query.
+    pub fn apply_tags(&self, ops: &TagOps) -> Result<()> {
+        for message in self.search_messages()? {
+            message.apply(ops)?;
+        }
+        Ok(())
+    }
+}
+
+impl Database {
+    /// Run a `notmuch tag --batch` file: every line holds tag changes
+    /// followed by a query, e.g. `+inbox -unread -- tag:new`.
+    ///
+    /// Each line is applied within its own atomic operation.
+    pub fn tag_batch<R>(&self, reader: R) -> Result<()>
+    where
+        R: BufRead,
+    {
+        for (n, line) in reader.lines().enumerate() {
+            let line = line?;
+
+            let (ops, query) = match TagOps::parse_batch_line(&line).map_err(|e| at_line(n + 1, e))? {
+                Some(parsed) => parsed,
+                None => continue,
+            };
+
+            let _atomic = AtomicOperation::new(self)?;
+            self.create_query(&query)?.apply_tags(&ops)?;
+        }
+
+        Ok(())
+    }
+}
@@ -6,6 +6,7 @@ use from_variants::FromVariants;
 
 use ffi;
 use utils::ToStr;
+use Database;
 use Query;
 use Threads;
 use Messages;
@@ -43,6 +44,13 @@ impl Thread {
         }
     }
 
+    pub(crate) fn database(&self) -> &Database {
+        match &*self.owner {
+            ThreadOwner::Query(query) => query.database(),
+            ThreadOwner::Threads(threads) => threads.database(),
+        }
+    }
+
     pub fn id(&self) -> &str {
         let tid = unsafe { ffi::notmuch_thread_get_thread_id(self.ptr.0) };
         tid.to_str().unwrap()
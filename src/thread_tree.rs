@@ -0,0 +1,97 @@
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use error::Result;
+use Message;
+use MessageSummary;
+use Thread;
+
+/// A message within the reply tree of a thread.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ThreadNode {
+    /// The message, whose `matched` field tells whether it matches the query
+    /// the thread was found with.
+    pub message: MessageSummary,
+    /// Zero for the toplevel messages of the thread.
+    pub depth: usize,
+    /// Replies, oldest first.
+    pub children: Vec<ThreadNode>,
+}
+
+impl ThreadNode {
+    fn new(message: &Message, depth: usize) -> Result<Self> {
+        let children = message
+            .replies()
+            .map(|reply| ThreadNode::new(&reply, depth + 1))
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(ThreadNode {
+            message: message.snapshot()?,
+            depth,
+            children,
+        })
+    }
+
+    /// This node and all of its descendants, every node before its replies.
+    pub fn pre_order(&self) -> PreOrder<'_> {
+        PreOrder { stack: vec![self] }
+    }
+
+    /// This node and all of its descendants, every node after its replies.
+    pub fn post_order(&self) -> PostOrder<'_> {
+        PostOrder {
+            stack: vec![(self, 0)],
+        }
+    }
+}
+
+/// Iterator returned by `ThreadNode::pre_order`.
+#[derive(Clone, Debug)]
+pub struct PreOrder<'a> {
+    stack: Vec<&'a ThreadNode>,
+}
+
+impl<'a> Iterator for PreOrder<'a> {
+    type Item = &'a ThreadNode;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let node = self.stack.pop()?;
+        self.stack.extend(node.children.iter().rev());
+        Some(node)
+    }
+}
+
+/// Iterator returned by `ThreadNode::post_order`.
+#[derive(Clone, Debug)]
+pub struct PostOrder<'a> {
+    /// Nodes on the path from the root, with the index of the next child
+    /// to visit.
+    stack: Vec<(&'a ThreadNode, usize)>,
+}
+
+impl<'a> Iterator for PostOrder<'a> {
+    type Item = &'a ThreadNode;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let (node, next_child) = self.stack.last_mut()?;
+            match node.children.get(*next_child) {
+                Some(child) => {
+                    *next_child += 1;
+                    self.stack.push((child, 0));
+                }
+                None => return self.stack.pop().map(|(node, _)| node),
+            }
+        }
+    }
+}
+
+impl Thread {
+    /// The reply tree of the thread, one root per toplevel message.
+    pub fn tree(&self) -> Result<Vec<ThreadNode>> {
+        self.toplevel_messages()
+            .map(|message| ThreadNode::new(&message, 0))
+            .collect()
+    }
+}
@@ -5,6 +5,7 @@ use std::fmt::Debug;
 use from_variants::FromVariants;
 
 use ffi;
+use Database;
 use Query;
 use Thread;
 
@@ -41,6 +42,26 @@ impl Threads
             owner: Box::new(owner.into()),
         }
     }
+
+    pub(crate) fn database(&self) -> &Database {
+        match &*self.owner {
+            ThreadsOwner::Query(query) => query.database(),
+        }
+    }
+}
+
+impl Threads
+{
+    /// Move past the next `n` threads without looking at them, returning
+    /// how many were actually skipped.
+    pub(crate) fn skip_n(&mut self, n: usize) -> usize {
+        let mut skipped = 0;
+        while skipped < n && unsafe { ffi::notmuch_threads_valid(self.ptr.0) } != 0 {
+            unsafe { ffi::notmuch_threads_move_to_next(self.ptr.0) };
+            skipped += 1;
+        }
+        skipped
+    }
 }
 
 impl Iterator for Threads
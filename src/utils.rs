@@ -2,6 +2,8 @@ use libc;
 use std::borrow::Cow;
 use std::{ffi, str};
 
+use encoding_rs::Encoding;
+
 pub trait ToStr {
     fn to_str<'a>(&self) -> Result<&'a str, str::Utf8Error>;
 
@@ -49,3 +51,115 @@ impl ToString for *const libc::c_char {
         }
     }
 }
+
+/// Characters that are written verbatim by `hex_encode`, as in notmuch's
+/// `util/hex-escape.c`.
+const HEX_SAFE_CHARS: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+-_@=.,";
+
+/// Escape a tag or message id the way `notmuch dump` and `notmuch tag
+/// --batch` do: every byte outside of a small safe set becomes `%XX`.
+pub(crate) fn hex_encode(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for &b in s.as_bytes() {
+        if HEX_SAFE_CHARS.contains(&b) {
+            out.push(b as char);
+        } else {
+            out.push_str(&format!("%{:02x}", b));
+        }
+    }
+    out
+}
+
+/// Reverse `hex_encode`. Returns `None` for malformed escapes or if the
+/// decoded bytes are not valid UTF-8.
+pub(crate) fn hex_decode(s: &str) -> Option<String> {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let hex = s.get(i + 1..i + 3)?;
+            if !hex.bytes().all(|c| c.is_ascii_hexdigit()) {
+                return None;
+            }
+            out.push(u8::from_str_radix(hex, 16).ok()?);
+            i += 3;
+        } else {
+            out.push(bytes[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8(out).ok()
+}
+
+/// Decode base64, skipping whitespace. Returns `None` for any other
+/// character outside of the base64 alphabet.
+pub(crate) fn base64_decode(s: &str) -> Option<Vec<u8>> {
+    let mut out = Vec::with_capacity(s.len() * 3 / 4);
+    let mut acc: u32 = 0;
+    let mut bits = 0;
+    for c in s.bytes() {
+        let value = match c {
+            b'A'..=b'Z' => c - b'A',
+            b'a'..=b'z' => c - b'a' + 26,
+            b'0'..=b'9' => c - b'0' + 52,
+            b'+' => 62,
+            b'/' => 63,
+            b'=' => break,
+            c if c.is_ascii_whitespace() => continue,
+            _ => return None,
+        };
+        acc = (acc << 6) | u32::from(value);
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            out.push((acc >> bits) as u8);
+        }
+    }
+    Some(out)
+}
+
+/// Decode `bytes` in the given MIME charset, replacing malformed sequences.
+/// Returns `None` for unknown charsets.
+pub(crate) fn decode_charset(charset: &str, bytes: &[u8]) -> Option<String> {
+    let encoding = Encoding::for_label(charset.trim().as_bytes())?;
+    let (text, _) = encoding.decode_without_bom_handling(bytes);
+    Some(text.into_owned())
+}
+
+/// Decode quoted-printable. Malformed escapes are kept as they are, as RFC
+/// 2045 recommends.
+pub(crate) fn quoted_printable_decode(bytes: &[u8]) -> Vec<u8> {
+    let hex = |c: u8| (c as char).to_digit(16).map(|d| d as u8);
+
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] != b'=' {
+            out.push(bytes[i]);
+            i += 1;
+            continue;
+        }
+
+        // A soft line break, possibly after trailing whitespace.
+        let rest = &bytes[i + 1..];
+        let ws = rest.iter().take_while(|&&c| c == b' ' || c == b'\t').count();
+        if rest[ws..].starts_with(b"\r\n") {
+            i += 1 + ws + 2;
+        } else if rest[ws..].starts_with(b"\n") || ws == rest.len() {
+            i += 1 + ws + 1;
+        } else {
+            match (rest.first().cloned().and_then(hex), rest.get(1).cloned().and_then(hex)) {
+                (Some(hi), Some(lo)) => {
+                    out.push(hi << 4 | lo);
+                    i += 3;
+                }
+                _ => {
+                    out.push(b'=');
+                    i += 1;
+                }
+            }
+        }
+    }
+    out
+}
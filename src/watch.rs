@@ -0,0 +1,365 @@
+use std::collections::HashMap;
+use std::ffi::OsString;
+use std::io;
+use std::mem;
+use std::os::unix::io::AsRawFd;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use inotify::{Event, EventMask, Inotify, WatchDescriptor, WatchMask};
+use libc;
+
+use error::{Error, Result};
+use scan::Scanner;
+use Database;
+
+/// A change `Watcher` applied to the database.
+#[derive(Debug)]
+pub enum WatchEvent {
+    /// A new file was indexed.
+    Added(PathBuf),
+    /// A file was removed from disk and from the database.
+    Removed(PathBuf),
+    /// A file moved, as `(from, to)`, and the message kept its tags.
+    Renamed(PathBuf, PathBuf),
+    /// A file or directory could not be indexed or removed.
+    Failed(PathBuf, Error),
+}
+
+/// How long to wait for the second half of a move, before treating it as a
+/// removal.
+const MOVE_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// A file or directory that moved away, waiting for the event telling
+/// where to.
+struct PendingMove {
+    cookie: u32,
+    path: PathBuf,
+    is_dir: bool,
+    since: Instant,
+}
+
+/// Follows the mail root with inotify and applies changes to the database
+/// as they happen, the way `notmuch new` would.
+///
+/// New files are tagged with `new.tags`, `new.ignore` is honoured and
+/// maildir flags are synchronized to tags if `maildir.synchronize_flags` is
+/// set. Files already on disk when watching starts are left alone; run
+/// `Database::scan_new` for those.
+///
+/// Files moving within the mail root keep their tags, even if the two
+/// halves of the move are reported separately. Something moved out of the
+/// mail root is removed once it is clear that it didn't move elsewhere
+/// within it.
+///
+/// If the kernel drops events because they came in faster than they were
+/// read, the whole mail root is compared with the database again, as
+/// `Database::scan_new` does.
+pub struct Watcher<'d> {
+    scanner: Scanner<'d>,
+    inotify: Inotify,
+    watches: HashMap<WatchDescriptor, PathBuf>,
+    buffer: Vec<u8>,
+    pending_moves: Vec<PendingMove>,
+    /// Changes that could not be followed, reported with the next batch.
+    failed: Vec<(PathBuf, Error)>,
+}
+
+fn watch_mask() -> WatchMask {
+    WatchMask::CREATE
+        | WatchMask::CLOSE_WRITE
+        | WatchMask::MOVED_TO
+        | WatchMask::MOVED_FROM
+        | WatchMask::DELETE
+        | WatchMask::ONLYDIR
+}
+
+impl<'d> Watcher<'d> {
+    fn new(database: &'d Database) -> Result<Self> {
+        let mut watcher = Watcher {
+            scanner: Scanner::new(database)?,
+            inotify: Inotify::init()?,
+            watches: HashMap::new(),
+            buffer: vec![0; 4096],
+            pending_moves: vec![],
+            failed: vec![],
+        };
+
+        let root = watcher.scanner.root().to_path_buf();
+        watcher.add_directory(&root, false)?;
+
+        Ok(watcher)
+    }
+
+    /// Watch `path` and everything below it. With `index`, the files found
+    /// are queued for indexing, for directories that appeared while
+    /// watching.
+    ///
+    /// Failures below `path`, e.g. because a directory vanished again right
+    /// away, are recorded and don't stop the others from being added.
+    fn add_directory(&mut self, path: &Path, index: bool) -> Result<()> {
+        let wd = self.inotify.add_watch(path, watch_mask())?;
+        self.watches.insert(wd, path.to_path_buf());
+
+        let (files, subdirs) = self.scanner.list_directory(path)?;
+        if index {
+            for name in files {
+                let file = path.join(name);
+                if let Err(err) = self.scanner.file_added(&file) {
+                    self.failed.push((file, err));
+                }
+            }
+        }
+        for name in subdirs {
+            let subdir = path.join(name);
+            if let Err(err) = self.add_directory(&subdir, index) {
+                self.failed.push((subdir, err));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Stop watching `path` and everything below it, which moved away.
+    fn remove_directory(&mut self, path: &Path) {
+        let gone: Vec<WatchDescriptor> = self
+            .watches
+            .iter()
+            .filter(|(_, p)| p.starts_with(path))
+            .map(|(wd, _)| wd.clone())
+            .collect();
+
+        for wd in gone {
+            self.watches.remove(&wd);
+            // The watch may already be gone along with the directory.
+            let _ = self.inotify.rm_watch(wd);
+        }
+    }
+
+    /// Queue the removal of something that is gone from `path`.
+    fn moved_away(&mut self, path: &Path, is_dir: bool) -> Result<()> {
+        if is_dir {
+            self.remove_directory(path);
+            self.scanner.scan_vanished_directory(path)
+        } else {
+            self.scanner.file_removed(path);
+            Ok(())
+        }
+    }
+
+    /// Treat the moves that waited for too long as removals.
+    fn expire_moves(&mut self, now: Instant) {
+        let (expired, pending): (Vec<_>, Vec<_>) = mem::take(&mut self.pending_moves)
+            .into_iter()
+            .partition(|pending| now.duration_since(pending.since) >= MOVE_TIMEOUT);
+        self.pending_moves = pending;
+
+        for pending in expired {
+            if let Err(err) = self.moved_away(&pending.path, pending.is_dir) {
+                self.failed.push((pending.path, err));
+            }
+        }
+    }
+
+    /// Wait up to `timeout` for events to read.
+    fn poll(&self, timeout: Duration) -> Result<bool> {
+        let mut fd = libc::pollfd {
+            fd: self.inotify.as_raw_fd(),
+            events: libc::POLLIN,
+            revents: 0,
+        };
+        // Round up, so that a timeout below a millisecond doesn't spin.
+        let millis = timeout.as_millis() as libc::c_int + 1;
+
+        match unsafe { libc::poll(&mut fd, 1, millis) } {
+            -1 => {
+                let err = io::Error::last_os_error();
+                if err.kind() == io::ErrorKind::Interrupted {
+                    Ok(false)
+                } else {
+                    Err(err.into())
+                }
+            }
+            n => Ok(n > 0),
+        }
+    }
+
+    /// Block until something changes below the mail root, apply the changes
+    /// and pass them on to `callback`.
+    ///
+    /// Changes that can't be followed or applied are passed on as
+    /// `WatchEvent::Failed`; only errors reading events from inotify end the
+    /// wait with an error.
+    pub fn wait<F>(&mut self, mut callback: F) -> Result<()>
+    where
+        F: FnMut(WatchEvent),
+    {
+        let mut buffer = mem::take(&mut self.buffer);
+        let result = self.read_events(&mut buffer);
+        self.buffer = buffer;
+        result?;
+
+        self.scanner.apply();
+
+        let report = self.scanner.take_report();
+        report.added.into_iter().map(WatchEvent::Added).for_each(&mut callback);
+        report
+            .renamed
+            .into_iter()
+            .map(|(from, to)| WatchEvent::Renamed(from, to))
+            .for_each(&mut callback);
+        report.removed.into_iter().map(WatchEvent::Removed).for_each(&mut callback);
+        report
+            .failed
+            .into_iter()
+            .chain(mem::take(&mut self.failed))
+            .map(|(path, err)| WatchEvent::Failed(path, err))
+            .for_each(&mut callback);
+
+        Ok(())
+    }
+
+    /// Keep applying changes until an error occurs.
+    pub fn run<F>(&mut self, mut callback: F) -> Result<()>
+    where
+        F: FnMut(WatchEvent),
+    {
+        loop {
+            self.wait(&mut callback)?;
+        }
+    }
+
+    /// Block until events arrive and handle them. If any of them moved
+    /// something away, keep reading until the other half of each move
+    /// arrived or timed out.
+    fn read_events(&mut self, buffer: &mut [u8]) -> Result<()> {
+        let events: Vec<_> = self
+            .inotify
+            .read_events_blocking(buffer)?
+            .map(|event| event.into_owned())
+            .collect();
+        self.handle_events(events);
+
+        loop {
+            let oldest = match self.pending_moves.iter().map(|pending| pending.since).min() {
+                Some(oldest) => oldest,
+                None => return Ok(()),
+            };
+
+            let now = Instant::now();
+            let waited = now.duration_since(oldest);
+            if waited >= MOVE_TIMEOUT {
+                self.expire_moves(now);
+            } else if self.poll(MOVE_TIMEOUT - waited)? {
+                let events: Vec<_> = self
+                    .inotify
+                    .read_events(buffer)?
+                    .map(|event| event.into_owned())
+                    .collect();
+                self.handle_events(events);
+            }
+        }
+    }
+
+    /// Events were lost, so queue the differences between the whole mail
+    /// root and the database, and watch the directories that appeared
+    /// meanwhile.
+    fn rescan(&mut self) {
+        // The rescan covers whatever moved away.
+        self.pending_moves.clear();
+
+        let root = self.scanner.root().to_path_buf();
+        if let Err(err) = self.add_directory(&root, false) {
+            self.failed.push((root.clone(), err));
+        }
+        if let Err(err) = self.scanner.scan_directory(&root) {
+            self.failed.push((root, err));
+        }
+    }
+
+    fn handle_events(&mut self, events: Vec<Event<OsString>>) {
+        for event in events {
+            if event.mask.contains(EventMask::Q_OVERFLOW) {
+                self.rescan();
+                continue;
+            }
+            if event.mask.contains(EventMask::IGNORED) {
+                self.watches.remove(&event.wd);
+                continue;
+            }
+
+            let path = match (self.watches.get(&event.wd), &event.name) {
+                (Some(dir), Some(name)) => dir.join(name),
+                _ => continue,
+            };
+            if self.scanner.is_ignored(&path) {
+                continue;
+            }
+
+            if let Err(err) = self.handle_event(&event, &path) {
+                self.failed.push((path, err));
+            }
+        }
+    }
+
+    fn handle_event(&mut self, event: &Event<OsString>, path: &Path) -> Result<()> {
+        let is_dir = event.mask.contains(EventMask::ISDIR);
+
+        if event.mask.contains(EventMask::MOVED_FROM) {
+            self.pending_moves.push(PendingMove {
+                cookie: event.cookie,
+                path: path.to_path_buf(),
+                is_dir,
+                since: Instant::now(),
+            });
+            return Ok(());
+        }
+
+        if event.mask.contains(EventMask::MOVED_TO) {
+            // Queue the removal of the old path along with the addition of
+            // the new one, so that the scanner sees a rename.
+            let from = self
+                .pending_moves
+                .iter()
+                .position(|pending| pending.cookie == event.cookie)
+                .map(|i| self.pending_moves.remove(i));
+            if let Some(from) = from {
+                self.moved_away(&from.path, from.is_dir)?;
+            }
+        }
+
+        if is_dir {
+            if event.mask.intersects(EventMask::CREATE | EventMask::MOVED_TO) {
+                if !is_maildir_tmp(path) {
+                    self.add_directory(path, true)?;
+                }
+            } else if event.mask.contains(EventMask::DELETE) {
+                self.moved_away(path, true)?;
+            }
+        } else if event.mask.intersects(EventMask::CLOSE_WRITE | EventMask::MOVED_TO) {
+            if !path.parent().is_some_and(is_maildir_tmp) {
+                self.scanner.file_added(path)?;
+            }
+        } else if event.mask.contains(EventMask::DELETE) {
+            self.scanner.file_removed(path);
+        }
+
+        Ok(())
+    }
+}
+
+/// Whether `path` is the `tmp` folder of a maildir, which holds messages
+/// that are still being delivered.
+fn is_maildir_tmp(path: &Path) -> bool {
+    path.file_name().is_some_and(|name| name == "tmp")
+        && path
+            .parent()
+            .is_some_and(|parent| parent.join("cur").is_dir() || parent.join("new").is_dir())
+}
+
+impl Database {
+    /// Start watching the mail root for changes, see `Watcher`.
+    pub fn watch(&self) -> Result<Watcher<'_>> {
+        Watcher::new(self)
+    }
+}
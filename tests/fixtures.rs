@@ -190,16 +190,29 @@ impl NotmuchCommand {
         I: IntoIterator<Item=S>,
         S: AsRef<OsStr>
     {
+        self.command().args(args).status()?;
+        Ok(())
+    }
+
+    /// Run a notmuch command and return what it writes to stdout.
+    pub fn output<I, S>(&self, args: I) -> Result<Vec<u8>>
+    where
+        I: IntoIterator<Item=S>,
+        S: AsRef<OsStr>
+    {
+        Ok(self.command().args(args).output()?.stdout)
+    }
+
+    fn command(&self) -> Command {
         let cfg_fname = self.maildir_path.join("notmuch-config");
 
-        Command::new("notmuch")
+        let mut command = Command::new("notmuch");
+        command
             .env("NOTMUCH_CONFIG", &cfg_fname)
             .env_remove("NOTMUCH_DATABASE")
             .env_remove("NOTMUCH_PROFILE")
-            .env_remove("MAILDIR")
-            .args(args)
-            .status()?;
-        Ok(())
+            .env_remove("MAILDIR");
+        command
     }
 
 }
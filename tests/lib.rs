@@ -4,13 +4,41 @@ extern crate lettre;
 extern crate lettre_email;
 extern crate maildir;
 extern crate notmuch;
+#[cfg(feature = "serde")]
+extern crate serde_json;
 extern crate tempfile;
 
 mod fixtures;
 #[cfg(feature = "v0_32")]
+mod test_addresses;
+mod test_body;
+#[cfg(feature = "v0_32")]
 mod test_config;
 mod test_database;
+mod test_dump;
+#[cfg(feature = "v0_32")]
+mod test_format;
+#[cfg(feature = "v0_32")]
+mod test_handle;
+mod test_headers;
+#[cfg(feature = "v0_32")]
+mod test_hooks;
+#[cfg(feature = "v0_32")]
+mod test_insert;
 mod test_message;
+mod test_mime;
+mod test_named_queries;
 mod test_query;
+mod test_query_expr;
+mod test_query_parser;
+#[cfg(feature = "v0_32")]
+mod test_reply;
+#[cfg(feature = "v0_32")]
+mod test_scan;
+#[cfg(feature = "v0_32")]
+mod test_snapshot;
+mod test_tag_ops;
 mod test_tags;
 mod test_thread;
+#[cfg(feature = "watch")]
+mod test_watch;
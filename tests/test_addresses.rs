@@ -0,0 +1,176 @@
+use std::fs;
+
+use fixtures::{MailBox, NotmuchCommand};
+use notmuch::{Address, AddressCount, AddressOptions, AddressOrder, Deduplicate};
+
+const MESSAGES: &[&[u8]] = &[
+    b"From: Alice <alice@example.com>
+To: bob@example.com
+Cc: Carol <carol@example.com>
+Subject: one
+Date: Mon, 01 Jan 2001 10:00:00 +0000
+Message-ID: <one@example.com>
+
+one
+",
+    b"From: \"Alice A.\" <ALICE@example.com>
+To: bob@example.com
+Subject: two
+Date: Tue, 02 Jan 2001 10:00:00 +0000
+Message-ID: <two@example.com>
+
+two
+",
+    b"From: Alice <alice@example.com>
+To: Bob <bob@example.com>
+Subject: three
+Date: Wed, 03 Jan 2001 10:00:00 +0000
+Message-ID: <three@example.com>
+
+three
+",
+];
+
+// The same mailbox as in the first message, but with a capitalized address
+const CASE_MESSAGES: &[&[u8]] = &[
+    MESSAGES[0],
+    b"From: Alice <Alice@example.com>
+Subject: two
+Date: Tue, 02 Jan 2001 10:00:00 +0000
+Message-ID: <two@example.com>
+
+two
+",
+    MESSAGES[2],
+];
+
+struct AddressesFixture {
+    // Return a query for three messages, oldest first
+    pub mailbox: MailBox,
+    pub query: notmuch::Query,
+}
+
+impl AddressesFixture {
+    pub fn new() -> Self {
+        Self::with_messages(MESSAGES)
+    }
+
+    pub fn with_messages(messages: &[&[u8]]) -> Self {
+        let mailbox = MailBox::new();
+        for (i, message) in messages.iter().enumerate() {
+            fs::write(mailbox.path().join("new").join(i.to_string()), message).unwrap();
+        }
+
+        let cmd = NotmuchCommand::new(&mailbox.path());
+        cmd.run(vec!["new"]).unwrap();
+
+        let database = notmuch::Database::open_with_config(
+            Some(&mailbox.path()),
+            notmuch::DatabaseMode::ReadOnly,
+            Some(mailbox.path().join("notmuch-config")),
+            None,
+        )
+        .unwrap();
+        let query = notmuch::Query::create(&database, "*").unwrap();
+        query.set_sort(notmuch::Sort::OldestFirst);
+
+        Self { mailbox, query }
+    }
+}
+
+fn count(name: Option<&str>, address: &str, count: usize) -> AddressCount {
+    AddressCount {
+        address: Address::new(name, address),
+        count,
+    }
+}
+
+#[test]
+fn test_senders() {
+    let fixture = AddressesFixture::new();
+
+    assert_eq!(
+        fixture.query.addresses(AddressOptions::default()).unwrap(),
+        vec![
+            count(Some("Alice"), "alice@example.com", 2),
+            count(Some("Alice A."), "ALICE@example.com", 1),
+        ]
+    );
+}
+
+#[test]
+fn test_deduplicate() {
+    let fixture = AddressesFixture::new();
+
+    let options = AddressOptions {
+        deduplicate: Deduplicate::Address,
+        ..AddressOptions::default()
+    };
+    assert_eq!(
+        fixture.query.addresses(options).unwrap(),
+        vec![count(Some("Alice"), "alice@example.com", 3)]
+    );
+
+    let options = AddressOptions {
+        deduplicate: Deduplicate::No,
+        ..AddressOptions::default()
+    };
+    assert_eq!(fixture.query.addresses(options).unwrap().len(), 3);
+}
+
+#[test]
+fn test_deduplicate_mailbox_case() {
+    let fixture = AddressesFixture::with_messages(CASE_MESSAGES);
+
+    let options = AddressOptions {
+        deduplicate: Deduplicate::Mailbox,
+        ..AddressOptions::default()
+    };
+    assert_eq!(
+        fixture.query.addresses(options).unwrap(),
+        vec![
+            count(Some("Alice"), "alice@example.com", 2),
+            count(Some("Alice"), "Alice@example.com", 1),
+        ]
+    );
+}
+
+#[test]
+fn test_recipients() {
+    let fixture = AddressesFixture::new();
+
+    let options = AddressOptions {
+        sender: false,
+        recipients: true,
+        order: AddressOrder::Count,
+        ..AddressOptions::default()
+    };
+    assert_eq!(
+        fixture.query.addresses(options).unwrap(),
+        vec![
+            count(None, "bob@example.com", 2),
+            count(Some("Carol"), "carol@example.com", 1),
+            count(Some("Bob"), "bob@example.com", 1),
+        ]
+    );
+}
+
+#[test]
+fn test_order() {
+    let fixture = AddressesFixture::new();
+
+    let options = AddressOptions {
+        sender: true,
+        recipients: true,
+        deduplicate: Deduplicate::Address,
+        order: AddressOrder::Address,
+    };
+    assert_eq!(
+        fixture.query.addresses(options).unwrap(),
+        vec![
+            count(Some("Alice"), "alice@example.com", 3),
+            count(None, "bob@example.com", 3),
+            count(Some("Carol"), "carol@example.com", 1),
+        ]
+    );
+}
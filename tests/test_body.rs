@@ -0,0 +1,144 @@
+use std::fs;
+
+use notmuch::{BodyOptions, MimePart};
+
+use fixtures::MailBox;
+
+const REPLY: &[u8] = b"Subject: Re: lunch
+Content-Type: multipart/alternative; boundary=b
+
+--b
+Content-Type: text/plain; charset=utf-8
+
+Sounds good, see you there.
+
+On Mon, 1 Jun 2020, Some One wrote:
+> Lunch at noon?
+>
+> -- one
+
+-- \r
+Some Hacker
+--b
+Content-Type: text/html
+
+<p>Sounds <b>good</b></p>
+--b--
+";
+
+const HTML_ONLY: &[u8] = b"Content-Type: text/html; charset=iso-8859-1
+
+<html><head><title>t</title><style>p { color: red; }</style></head>
+<body><p>Caf\xe9 &amp; <i>bar</i>&nbsp;&nbsp;&#x263A;</p>
+<ul><li>one</li><li>two</li></ul>line<br>break
+<blockquote>quoted<br>text</blockquote>
+<!-- <p>hidden</p> --><script>alert('x')</script>done</body></html>
+";
+
+mod parse {
+    use super::*;
+
+    #[test]
+    fn test_prefers_plain() {
+        let text = MimePart::parse(REPLY).body_text(&BodyOptions::default()).unwrap();
+
+        assert_eq!(
+            text,
+            "Sounds good, see you there.\n\nOn Mon, 1 Jun 2020, Some One wrote:\n> Lunch at noon?\n>\n> -- one\n\n-- \nSome Hacker\n"
+        );
+    }
+
+    #[test]
+    fn test_strip() {
+        let options = BodyOptions {
+            strip_quotes: true,
+            strip_signature: true,
+        };
+        let text = MimePart::parse(REPLY).body_text(&options).unwrap();
+
+        assert_eq!(text, "Sounds good, see you there.\n");
+    }
+
+    #[test]
+    fn test_html_fallback() {
+        let text = MimePart::parse(HTML_ONLY).body_text(&BodyOptions::default()).unwrap();
+
+        assert_eq!(
+            text,
+            "Café & bar  \u{263a}\n* one\n* two\nline\nbreak\n> quoted\n> text\ndone\n"
+        );
+
+        let options = BodyOptions {
+            strip_quotes: true,
+            ..BodyOptions::default()
+        };
+        let text = MimePart::parse(HTML_ONLY).body_text(&options).unwrap();
+        assert!(!text.contains("quoted"));
+    }
+
+    #[test]
+    fn test_skips_attachments() {
+        let message = b"Content-Type: multipart/mixed; boundary=b
+
+--b
+Content-Type: text/plain
+Content-Disposition: attachment; filename=notes.txt
+
+not the body
+--b
+Content-Type: text/html
+
+<p>the body</p>
+--b--
+";
+        let text = MimePart::parse(message).body_text(&BodyOptions::default());
+        assert_eq!(text.unwrap(), "the body\n");
+
+        let text = MimePart::parse(b"Content-Type: image/png\n\n").body_text(&BodyOptions::default());
+        assert!(text.is_none());
+    }
+
+    #[test]
+    fn test_skips_forwarded() {
+        let message = b"Content-Type: multipart/mixed; boundary=b
+
+--b
+Content-Type: text/html
+
+<p>see below</p>
+--b
+Content-Type: message/rfc822
+
+Subject: original
+
+the forwarded body
+--b--
+";
+        let root = MimePart::parse(message);
+        assert_eq!(root.body_text(&BodyOptions::default()).unwrap(), "see below\n");
+
+        // The forwarded message has a text of its own.
+        let forwarded = root.find(3).unwrap();
+        assert_eq!(forwarded.body_text(&BodyOptions::default()).unwrap(), "the forwarded body\n");
+    }
+}
+
+mod message {
+    use super::*;
+
+    #[test]
+    fn test_body_text() {
+        let mailbox = MailBox::new();
+        let database = notmuch::Database::create(&mailbox.path()).unwrap();
+
+        let filename = mailbox.path().join("new").join("reply");
+        fs::write(&filename, REPLY).unwrap();
+
+        let msg = database.index_file(&filename, None).unwrap();
+        let options = BodyOptions {
+            strip_quotes: true,
+            strip_signature: true,
+        };
+        assert_eq!(msg.body_text(options).unwrap().unwrap(), "Sounds good, see you there.\n");
+    }
+}
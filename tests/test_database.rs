@@ -150,6 +150,43 @@ mod revision {
 
     }
 
+    #[test]
+    fn test_changes_since(){
+        let mailbox = MailBox::new();
+        let db = notmuch::Database::create(&mailbox.path()).unwrap();
+
+        let (_, filename) = mailbox.deliver(None, None, None, None, vec![], true, None, false, false, false).unwrap();
+        let msg0 = db.index_file(&filename, None).unwrap();
+        let (_, filename) = mailbox.deliver(None, None, None, None, vec![], true, None, false, false, false).unwrap();
+        let msg1 = db.index_file(&filename, None).unwrap();
+
+        let rev0 = db.revision();
+        let changes = db.changes_since(&rev0).unwrap();
+        assert_eq!(changes.messages.count(), 0);
+        assert_eq!(changes.revision, rev0);
+
+        msg1.add_tag("changed").unwrap();
+        let changes = db.changes_since(&rev0).unwrap();
+        assert!(changes.revision > rev0);
+        let ids: Vec<String> = changes.messages.map(|m| m.id().into_owned()).collect();
+        assert_eq!(ids, vec![msg1.id().into_owned()]);
+        assert!(!ids.contains(&msg0.id().into_owned()));
+
+        let changes = db.changes_since(&changes.revision).unwrap();
+        assert_eq!(changes.messages.count(), 0);
+    }
+
+    #[test]
+    fn test_changes_since_diff_db(){
+        let mailbox0 = MailBox::new();
+        let db0 = notmuch::Database::create(&mailbox0.path()).unwrap();
+
+        let mailbox1 = MailBox::new();
+        let db1 = notmuch::Database::create(&mailbox1.path()).unwrap();
+
+        assert!(db1.changes_since(&db0.revision()).is_err());
+    }
+
     // TODO: add tests for revisions comparisons
 
 }
@@ -337,3 +374,57 @@ mod query {
     }
 }
 
+mod directory {
+    use super::*;
+
+    #[test]
+    fn test_child_files() {
+        let db = DatabaseFixture::new();
+
+        let dir = db.database.directory(db.mailbox.path().join("new")).unwrap().unwrap();
+        assert_eq!(dir.child_files().count(), 3);
+        assert_eq!(dir.child_directories().count(), 0);
+    }
+
+    #[test]
+    fn test_child_directories() {
+        let db = DatabaseFixture::new();
+
+        // Only directories holding indexed mail are sure to have a record.
+        let dir = db.database.directory(db.mailbox.path()).unwrap().unwrap();
+        let children: Vec<_> = dir.child_directories().collect();
+        assert!(children.iter().any(|c| c.to_str() == Some("new")));
+    }
+
+    #[test]
+    fn test_mtime() {
+        let db = DatabaseFixture::new();
+
+        let dir = db.database.directory(db.mailbox.path().join("new")).unwrap().unwrap();
+        assert!(dir.mtime() > 0);
+
+        dir.set_mtime(42).unwrap();
+        assert_eq!(dir.mtime(), 42);
+    }
+
+    #[cfg(feature = "v0_21")]
+    #[test]
+    fn test_delete() {
+        let db = DatabaseFixture::new();
+
+        let dir = db.database.directory(db.mailbox.path().join("new")).unwrap().unwrap();
+        dir.delete().unwrap();
+
+        assert!(db.database.directory(db.mailbox.path().join("new")).unwrap().is_none());
+    }
+
+    #[cfg(feature = "v0_21")]
+    #[test]
+    fn test_delete_shared() {
+        let db = DatabaseFixture::new();
+
+        let dir = db.database.directory(db.mailbox.path().join("new")).unwrap().unwrap();
+        let _files = dir.child_files();
+        assert!(dir.delete().is_err());
+    }
+}
@@ -0,0 +1,116 @@
+use fixtures::MailBox;
+
+struct DumpFixture {
+    // Return a read-write Database with a single, tagged message.
+    pub mailbox: MailBox,
+    pub database: notmuch::Database,
+    pub msgid: String,
+}
+
+impl DumpFixture {
+    pub fn new() -> Self {
+        let mailbox = MailBox::new();
+        let database = notmuch::Database::create(&mailbox.path()).unwrap();
+
+        let (msgid, filename) = mailbox.deliver(None, None, None, None, vec![], true, None, false, false, false).unwrap();
+        let msg = database.index_file(&filename, None).unwrap();
+        msg.add_tag("inbox").unwrap();
+        msg.add_tag("with space").unwrap();
+        msg.add_property("testkey", "test value").unwrap();
+
+        Self {
+            mailbox,
+            database,
+            msgid,
+        }
+    }
+
+    fn dump(&self, options: notmuch::DumpOptions) -> String {
+        let mut out = vec![];
+        self.database.dump("", &mut out, options).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+}
+
+mod dump {
+    use super::*;
+
+    #[test]
+    fn test_dump() {
+        let db = DumpFixture::new();
+        let dump = db.dump(Default::default());
+        let lines: Vec<&str> = dump.lines().collect();
+
+        assert_eq!(lines[0], "#notmuch-dump batch-tag:3 properties,tags");
+        assert_eq!(lines[1], format!("#= {} testkey=test%20value", db.msgid));
+        assert_eq!(lines[2], format!("+inbox +with%20space -- id:{}", db.msgid));
+        assert_eq!(lines.len(), 3);
+    }
+
+    #[test]
+    fn test_dump_tags_only() {
+        let db = DumpFixture::new();
+        let dump = db.dump(notmuch::DumpOptions {
+            tags: true,
+            properties: false,
+        });
+
+        assert!(dump.starts_with("#notmuch-dump batch-tag:3 tags\n"));
+        assert!(!dump.contains("#="));
+    }
+
+    #[test]
+    fn test_restore_replace() {
+        let db = DumpFixture::new();
+        let dump = db.dump(Default::default());
+
+        let msg = db.database.find_message(&db.msgid).unwrap().unwrap();
+        msg.remove_tag("inbox").unwrap();
+        msg.add_tag("extra").unwrap();
+        msg.remove_all_properties(None).unwrap();
+
+        db.database.restore(dump.as_bytes(), Default::default()).unwrap();
+
+        let msg = db.database.find_message(&db.msgid).unwrap().unwrap();
+        let tags: Vec<String> = msg.tags().collect();
+        assert_eq!(tags, vec!["inbox".to_string(), "with space".to_string()]);
+        assert_eq!(msg.property("testkey").unwrap(), "test value");
+    }
+
+    #[test]
+    fn test_restore_accumulate() {
+        let db = DumpFixture::new();
+        let dump = db.dump(Default::default());
+
+        let msg = db.database.find_message(&db.msgid).unwrap().unwrap();
+        msg.remove_tag("inbox").unwrap();
+        msg.add_tag("extra").unwrap();
+
+        let options = notmuch::RestoreOptions {
+            accumulate: true,
+            ..Default::default()
+        };
+        db.database.restore(dump.as_bytes(), options).unwrap();
+
+        let msg = db.database.find_message(&db.msgid).unwrap().unwrap();
+        let tags: Vec<String> = msg.tags().collect();
+        assert!(tags.iter().any(|x| x == "inbox"));
+        assert!(tags.iter().any(|x| x == "extra"));
+    }
+
+    #[test]
+    fn test_restore_unknown_message() {
+        let db = DumpFixture::new();
+
+        let dump = "+inbox -- id:not-a-message@example.com\n";
+        assert!(db.database.restore(dump.as_bytes(), Default::default()).is_ok());
+    }
+
+    #[test]
+    fn test_restore_malformed() {
+        let db = DumpFixture::new();
+
+        let dump = "+inbox id:foo@example.com\n";
+        assert!(db.database.restore(dump.as_bytes(), Default::default()).is_err());
+    }
+}
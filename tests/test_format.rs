@@ -0,0 +1,272 @@
+use std::fs;
+
+use fixtures::{MailBox, NotmuchCommand};
+use notmuch::{FormatOptions, Formatter};
+
+const FIRST: &[u8] = b"From: Some One <one@example.com>
+To: dst@example.com
+Subject: hello
+Date: Mon, 01 Jan 2001 10:00:00 +0000
+Message-ID: <one@example.com>
+
+first\r
+";
+
+const SECOND: &[u8] = b"From: dst@example.com
+To: Some One <one@example.com>
+Subject: Re: hello
+Date: Tue, 02 Jan 2001 10:00:00 +0000
+Message-ID: <two@example.com>
+In-Reply-To: <one@example.com>
+Content-Type: multipart/mixed; boundary=b
+
+--b
+Content-Type: text/plain
+
+second
+--b
+Content-Type: text/html
+
+<p>second</p>
+--b
+Content-Type: application/pdf
+Content-Disposition: attachment; filename=a.pdf
+Content-Transfer-Encoding: base64
+
+AAECAw==
+--b--
+";
+
+struct FormatFixture {
+    // Return a database with a thread of two messages
+    pub mailbox: MailBox,
+    pub database: notmuch::Database,
+}
+
+impl FormatFixture {
+    pub fn new() -> Self {
+        let mailbox = MailBox::new();
+        fs::write(mailbox.path().join("new").join("first"), FIRST).unwrap();
+        fs::write(mailbox.path().join("new").join("second"), SECOND).unwrap();
+
+        let cmd = NotmuchCommand::new(&mailbox.path());
+        cmd.run(vec!["new"]).unwrap();
+
+        let database = notmuch::Database::open_with_config(
+            Some(&mailbox.path()),
+            notmuch::DatabaseMode::ReadOnly,
+            Some(mailbox.path().join("notmuch-config")),
+            None,
+        )
+        .unwrap();
+
+        Self { mailbox, database }
+    }
+
+    fn output<F>(&self, write: F) -> String
+    where
+        F: FnOnce(&mut Vec<u8>),
+    {
+        let mut out = vec![];
+        write(&mut out);
+        String::from_utf8(out).unwrap()
+    }
+}
+
+#[test]
+fn test_unsupported_version() {
+    for &version in &[3, 6] {
+        let options = FormatOptions {
+            format_version: version,
+            ..FormatOptions::default()
+        };
+        assert!(Formatter::json(options).is_err());
+        assert!(Formatter::sexp(options).is_err());
+        assert!(Formatter::text0(options).is_err());
+    }
+}
+
+mod json {
+    use super::*;
+
+    #[test]
+    fn test_search_threads() {
+        let fixture = FormatFixture::new();
+        let formatter = Formatter::json(FormatOptions::default()).unwrap();
+
+        let query = notmuch::Query::create(&fixture.database, "id:two@example.com").unwrap();
+        let out = fixture.output(|out| formatter.search_threads(query.search_threads().unwrap(), out).unwrap());
+
+        assert!(out.starts_with("[{\"thread\": \""));
+        assert!(out.contains("\"timestamp\": 978429600, \"date_relative\": \""));
+        assert!(out.contains("\"matched\": 1, \"total\": 2, "));
+        assert!(out.contains("\"subject\": \"hello\""));
+        assert!(out.contains("\"query\": [\"id:two@example.com\", \"id:one@example.com\"]"));
+        assert!(out.contains("\"tags\": [\"inbox\", \"unread\"]"));
+        assert!(out.ends_with("}]\n"));
+    }
+
+    #[test]
+    fn test_search_messages() {
+        let fixture = FormatFixture::new();
+        let formatter = Formatter::json(FormatOptions::default()).unwrap();
+
+        let query = notmuch::Query::create(&fixture.database, "hello").unwrap();
+        query.set_sort(notmuch::Sort::OldestFirst);
+        let out = fixture.output(|out| formatter.search_messages(query.search_messages().unwrap(), out).unwrap());
+
+        assert_eq!(out, "[\"one@example.com\",\n\"two@example.com\"]\n");
+    }
+
+    #[test]
+    fn test_show_thread() {
+        let fixture = FormatFixture::new();
+        let formatter = Formatter::json(FormatOptions::default()).unwrap();
+
+        let query = notmuch::Query::create(&fixture.database, "id:two@example.com").unwrap();
+        let thread = query.search_threads().unwrap().next().unwrap();
+        let out = fixture.output(|out| formatter.show_thread(&thread, out).unwrap());
+
+        assert!(out.starts_with("[[{\"id\": \"one@example.com\", \"match\": false, \"excluded\": false, \"filename\": [\""));
+        assert!(out.contains("\"body\": [{\"id\": 1, \"content-type\": \"text/plain\", \"content\": \"first\\n\"}]"));
+        assert!(out.contains("\"crypto\": {}, \"headers\": {\"Subject\": \"hello\", \"From\": \"Some One <one@example.com>\", \"To\": \"dst@example.com\", \"Date\": \"Mon, 01 Jan 2001 10:00:00 +0000\"}}"));
+        assert!(out.contains("[[{\"id\": \"two@example.com\", \"match\": true, "));
+        assert!(out.contains("{\"id\": 3, \"content-type\": \"text/html\", \"content-length\": 13}"));
+        assert!(out.contains(
+            "{\"id\": 4, \"content-type\": \"application/pdf\", \"content-disposition\": \"attachment\", \
+             \"filename\": \"a.pdf\", \"content-transfer-encoding\": \"base64\", \"content-length\": 8}"
+        ));
+    }
+
+    #[test]
+    fn test_show_message() {
+        let fixture = FormatFixture::new();
+        let options = FormatOptions {
+            format_version: 4,
+            body: false,
+            include_html: true,
+        };
+        let formatter = Formatter::json(options).unwrap();
+
+        let message = fixture.database.find_message("two@example.com").unwrap().unwrap();
+        let out = fixture.output(|out| formatter.show_message(&message, out).unwrap());
+
+        assert!(out.starts_with("{\"id\": \"two@example.com\", "));
+        assert!(!out.contains("\"body\""));
+        assert!(!out.contains("\"crypto\""));
+        assert!(out.ends_with("\"Date\": \"Tue, 02 Jan 2001 10:00:00 +0000\"}}\n"));
+    }
+}
+
+mod sexp {
+    use super::*;
+
+    #[test]
+    fn test_search() {
+        let fixture = FormatFixture::new();
+        let formatter = Formatter::sexp(FormatOptions::default()).unwrap();
+
+        let query = notmuch::Query::create(&fixture.database, "hello").unwrap();
+        query.set_sort(notmuch::Sort::OldestFirst);
+        let out = fixture.output(|out| formatter.search_messages(query.search_messages().unwrap(), out).unwrap());
+        assert_eq!(out, "(\"one@example.com\"\n\"two@example.com\")\n");
+
+        let query = notmuch::Query::create(&fixture.database, "id:two@example.com").unwrap();
+        let out = fixture.output(|out| formatter.search_threads(query.search_threads().unwrap(), out).unwrap());
+        assert!(out.starts_with("((:thread \""));
+        assert!(out.contains(":matched 1 :total 2 "));
+        assert!(out.contains(":query (\"id:two@example.com\" \"id:one@example.com\") :tags (\"inbox\" \"unread\"))"));
+    }
+
+    #[test]
+    fn test_show_thread() {
+        let fixture = FormatFixture::new();
+        let formatter = Formatter::sexp(FormatOptions::default()).unwrap();
+
+        let query = notmuch::Query::create(&fixture.database, "id:two@example.com").unwrap();
+        let thread = query.search_threads().unwrap().next().unwrap();
+        let out = fixture.output(|out| formatter.show_thread(&thread, out).unwrap());
+
+        assert!(out.starts_with("(((:id \"one@example.com\" :match nil :excluded nil :filename (\""));
+        assert!(out.contains(":body ((:id 1 :content-type \"text/plain\" :content \"first\\n\"))"));
+        assert!(out.contains(":crypto () :headers (:Subject \"hello\" :From \"Some One <one@example.com>\""));
+        assert!(out.contains("(:id \"two@example.com\" :match t "));
+    }
+}
+
+mod text0 {
+    use super::*;
+
+    // Compare with the output of `notmuch search --format=text0`, byte for
+    // byte.
+    fn assert_like_notmuch(fixture: &FormatFixture, output: &str, out: Vec<u8>) {
+        let cmd = NotmuchCommand::new(&fixture.mailbox.path());
+        let expected = cmd
+            .output(vec!["search", "--format=text0", output, "hello"])
+            .unwrap();
+        assert_eq!(out, expected);
+    }
+
+    #[test]
+    fn test_search_threads() {
+        let fixture = FormatFixture::new();
+        let formatter = Formatter::text0(FormatOptions::default()).unwrap();
+
+        let query = notmuch::Query::create(&fixture.database, "hello").unwrap();
+        let mut out = vec![];
+        formatter.search_threads(query.search_threads().unwrap(), &mut out).unwrap();
+
+        assert!(out.starts_with(b"thread:"));
+        assert!(out.ends_with(b"\0"));
+        assert_like_notmuch(&fixture, "--output=threads", out);
+    }
+
+    #[test]
+    fn test_search_messages() {
+        let fixture = FormatFixture::new();
+        let formatter = Formatter::text0(FormatOptions::default()).unwrap();
+
+        let query = notmuch::Query::create(&fixture.database, "hello").unwrap();
+        let mut out = vec![];
+        formatter.search_messages(query.search_messages().unwrap(), &mut out).unwrap();
+
+        assert_eq!(out, b"id:two@example.com\0id:one@example.com\0");
+        assert_like_notmuch(&fixture, "--output=messages", out);
+    }
+
+    #[test]
+    fn test_search_tags() {
+        let fixture = FormatFixture::new();
+        let formatter = Formatter::text0(FormatOptions::default()).unwrap();
+
+        let query = notmuch::Query::create(&fixture.database, "hello").unwrap();
+        let mut out = vec![];
+        formatter.search_tags(query.search_messages().unwrap().collect_tags(), &mut out).unwrap();
+
+        assert_eq!(out, b"inbox\0unread\0");
+        assert_like_notmuch(&fixture, "--output=tags", out);
+    }
+
+    #[test]
+    fn test_search_files() {
+        let fixture = FormatFixture::new();
+        let formatter = Formatter::text0(FormatOptions::default()).unwrap();
+
+        let query = notmuch::Query::create(&fixture.database, "hello").unwrap();
+        let mut out = vec![];
+        formatter.search_files(query.search_messages().unwrap(), &mut out).unwrap();
+
+        assert_like_notmuch(&fixture, "--output=files", out);
+    }
+
+    #[test]
+    fn test_show_unsupported() {
+        let fixture = FormatFixture::new();
+        let formatter = Formatter::text0(FormatOptions::default()).unwrap();
+
+        let message = fixture.database.find_message("one@example.com").unwrap().unwrap();
+        let mut out = vec![];
+        assert!(formatter.show_message(&message, &mut out).is_err());
+        assert!(out.is_empty());
+    }
+}
@@ -0,0 +1,122 @@
+use std::thread;
+
+use fixtures::{MailBox, NotmuchCommand};
+use notmuch::{DatabaseHandle, TagOps};
+
+struct HandleFixture {
+    // Return a handle to a database with a thread of two messages
+    pub mailbox: MailBox,
+    pub handle: DatabaseHandle,
+    pub msgid: String,
+}
+
+impl HandleFixture {
+    pub fn new() -> Self {
+        let mailbox = MailBox::new();
+
+        let (msgid, _) = mailbox
+            .deliver(None, Some("foo".to_string()), None, None, vec![], true, None, false, false, false)
+            .unwrap();
+        mailbox
+            .deliver(
+                None,
+                Some("bar".to_string()),
+                None,
+                None,
+                vec![("In-Reply-To".to_string(), format!("<{}>", msgid))],
+                true,
+                None,
+                false,
+                false,
+                false,
+            )
+            .unwrap();
+
+        let cmd = NotmuchCommand::new(&mailbox.path());
+        cmd.run(vec!["new"]).unwrap();
+
+        let handle = DatabaseHandle::open_with_config(
+            Some(&mailbox.path()),
+            notmuch::DatabaseMode::ReadWrite,
+            Some(mailbox.path().join("notmuch-config")),
+            None,
+        )
+        .unwrap();
+
+        Self { mailbox, handle, msgid }
+    }
+}
+
+#[test]
+fn test_send_sync_clone() {
+    fn assert_send_sync_clone<T: Send + Sync + Clone>() {}
+    assert_send_sync_clone::<DatabaseHandle>();
+}
+
+#[test]
+fn test_run_from_threads() {
+    let fixture = HandleFixture::new();
+
+    let workers: Vec<_> = (0..4)
+        .map(|_| {
+            let handle = fixture.handle.clone();
+            thread::spawn(move || handle.run(|database| database.create_query("*")?.count_messages()))
+        })
+        .collect();
+
+    for worker in workers {
+        assert_eq!(worker.join().unwrap().unwrap(), 2);
+    }
+}
+
+#[test]
+fn test_commands() {
+    let fixture = HandleFixture::new();
+
+    assert_eq!(fixture.handle.count_messages("subject:foo").unwrap(), 1);
+
+    let messages = fixture.handle.search_messages("*").unwrap();
+    assert_eq!(messages.len(), 2);
+
+    let threads = fixture.handle.search_threads("*").unwrap();
+    assert_eq!(threads.len(), 1);
+    assert_eq!(threads[0].messages.len(), 2);
+
+    let message = fixture.handle.find_message(&fixture.msgid).unwrap().unwrap();
+    assert_eq!(message.id, fixture.msgid);
+    assert!(fixture.handle.find_message("missing@example.com").unwrap().is_none());
+
+    fixture
+        .handle
+        .tag("subject:foo", TagOps::new().with_add("handled").with_remove("unread"))
+        .unwrap();
+    assert_eq!(fixture.handle.count_messages("tag:handled").unwrap(), 1);
+    assert_eq!(fixture.handle.count_messages("tag:unread").unwrap(), 1);
+}
+
+#[test]
+fn test_panic_is_error() {
+    let fixture = HandleFixture::new();
+
+    let res: Result<(), notmuch::Error> = fixture.handle.run(|_| panic!("boom"));
+    match res {
+        Err(notmuch::Error::WorkerError(msg)) => assert_eq!(msg, "boom"),
+        other => panic!("unexpected result {:?}", other),
+    }
+
+    // The worker survives the panic.
+    assert_eq!(fixture.handle.count_messages("*").unwrap(), 2);
+}
+
+#[test]
+fn test_open_error() {
+    let mailbox = MailBox::new();
+
+    let res = DatabaseHandle::open_with_config(
+        Some(mailbox.path().join("missing")),
+        notmuch::DatabaseMode::ReadOnly,
+        Some(mailbox.path().join("missing-config")),
+        None,
+    );
+    assert!(res.is_err());
+}
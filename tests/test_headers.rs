@@ -0,0 +1,187 @@
+use notmuch::{decode_header, Address, HeaderDate, MessageId};
+
+use fixtures::MailBox;
+
+mod addresses {
+    use super::*;
+
+    #[test]
+    fn test_plain() {
+        assert_eq!(Address::parse_list("a@example.com"), vec![Address::new(None, "a@example.com")]);
+        assert_eq!(
+            Address::parse_list("John Doe <jd@example.com>, <x@example.com>"),
+            vec![
+                Address::new(Some("John Doe"), "jd@example.com"),
+                Address::new(None, "x@example.com"),
+            ]
+        );
+        assert_eq!(Address::parse_list(""), vec![]);
+    }
+
+    #[test]
+    fn test_quoted() {
+        assert_eq!(
+            Address::parse_list(r#""Doe, John \"JD\"" <jd@example.com>, b@example.com"#),
+            vec![
+                Address::new(Some("Doe, John \"JD\""), "jd@example.com"),
+                Address::new(None, "b@example.com"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_comments() {
+        assert_eq!(
+            Address::parse_list("jd@example.com (John Doe)"),
+            vec![Address::new(Some("John Doe"), "jd@example.com")]
+        );
+        assert_eq!(
+            Address::parse_list("John (the man) Doe <jd@example.com>"),
+            vec![Address::new(Some("John Doe"), "jd@example.com")]
+        );
+    }
+
+    #[test]
+    fn test_groups() {
+        assert_eq!(Address::parse_list("undisclosed-recipients:;"), vec![]);
+        assert_eq!(
+            Address::parse_list("Team: a@example.com, B <b@example.com>;, c@example.com"),
+            vec![
+                Address::new(None, "a@example.com"),
+                Address::new(Some("B"), "b@example.com"),
+                Address::new(None, "c@example.com"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_encoded() {
+        assert_eq!(
+            Address::parse_list("=?UTF-8?Q?J=C3=B6rg?= <j@example.com>"),
+            vec![Address::new(Some("Jörg"), "j@example.com")]
+        );
+    }
+
+    #[test]
+    fn test_display() {
+        assert_eq!(Address::new(Some("John Doe"), "jd@example.com").to_string(), "John Doe <jd@example.com>");
+        assert_eq!(Address::new(Some("Doe, John"), "jd@example.com").to_string(), r#""Doe, John" <jd@example.com>"#);
+        assert_eq!(Address::new(None, "jd@example.com").to_string(), "jd@example.com");
+    }
+}
+
+mod encoded_words {
+    use super::*;
+
+    #[test]
+    fn test_decode() {
+        assert_eq!(decode_header("plain text"), "plain text");
+        assert_eq!(decode_header("=?utf-8?B?SsO2cmc=?="), "Jörg");
+        assert_eq!(decode_header("=?ISO-8859-1?Q?Andr=E9_Pirard?="), "André Pirard");
+        assert_eq!(decode_header("Re: =?utf-8?q?caf=C3=A9?= time"), "Re: café time");
+    }
+
+    #[test]
+    fn test_adjacent() {
+        assert_eq!(decode_header("=?utf-8?q?a?= =?utf-8?q?b?="), "ab");
+        assert_eq!(decode_header("=?utf-8?q?a?=\r\n =?utf-8?q?b?= c"), "ab c");
+    }
+
+    #[test]
+    fn test_invalid() {
+        assert_eq!(decode_header("=?unknown?q?a?="), "=?unknown?q?a?=");
+        assert_eq!(decode_header("=?utf-8?x?a?= b"), "=?utf-8?x?a?= b");
+        assert_eq!(decode_header("a =? b"), "a =? b");
+    }
+}
+
+mod message_ids {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            MessageId::parse_list("<a@example.com> <b@example.com>\r\n\t<c@example.com>"),
+            vec![
+                MessageId("a@example.com".to_string()),
+                MessageId("b@example.com".to_string()),
+                MessageId("c@example.com".to_string()),
+            ]
+        );
+        assert_eq!(
+            MessageId::parse_list("<a@example.com> (John's message of Monday)"),
+            vec![MessageId("a@example.com".to_string())]
+        );
+        assert_eq!(MessageId::parse_list("garbage"), vec![]);
+    }
+}
+
+mod dates {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            HeaderDate::parse("Fri, 21 Nov 1997 09:55:06 -0600"),
+            Some(HeaderDate { timestamp: 880_127_706, offset: -360 })
+        );
+        assert_eq!(
+            HeaderDate::parse("21 Nov 97 09:55:06 GMT"),
+            Some(HeaderDate { timestamp: 880_106_106, offset: 0 })
+        );
+        assert_eq!(
+            HeaderDate::parse("Thu, 1 Jan 1970 01:00 +0100 (CET)"),
+            Some(HeaderDate { timestamp: 0, offset: 60 })
+        );
+        assert_eq!(HeaderDate::parse("yesterday"), None);
+        assert_eq!(HeaderDate::parse("32 Jan 2020 00:00:00 +0000"), None);
+    }
+}
+
+mod message {
+    use super::*;
+
+    #[test]
+    fn test_accessors() {
+        let mailbox = MailBox::new();
+        let database = notmuch::Database::create(&mailbox.path()).unwrap();
+
+        let (parent, _) = mailbox.deliver(None, None, None, None, vec![], true, None, false, false, false).unwrap();
+        let (_, filename) = mailbox
+            .deliver(
+                Some("=?utf-8?q?caf=C3=A9?=".to_string()),
+                None,
+                None,
+                None,
+                vec![
+                    ("Cc".to_string(), "Some One <cc@example.com>, =?utf-8?q?J=C3=B6rg?= <j@example.com>".to_string()),
+                    ("In-Reply-To".to_string(), format!("<{}>", parent)),
+                    ("References".to_string(), format!("<root@example.com> <{}>", parent)),
+                ],
+                true,
+                None,
+                false,
+                false,
+                false,
+            )
+            .unwrap();
+
+        let msg = database.index_file(&filename, None).unwrap();
+        assert_eq!(msg.from().unwrap(), vec![Address::new(None, "src@example.com")]);
+        assert_eq!(msg.to().unwrap(), vec![Address::new(None, "to@example.com")]);
+        assert_eq!(
+            msg.cc().unwrap(),
+            vec![
+                Address::new(Some("Some One"), "cc@example.com"),
+                Address::new(Some("Jörg"), "j@example.com"),
+            ]
+        );
+        assert_eq!(msg.subject().unwrap().unwrap(), "café");
+        assert_eq!(msg.in_reply_to().unwrap(), Some(MessageId(parent.clone())));
+        assert_eq!(
+            msg.references().unwrap(),
+            vec![MessageId("root@example.com".to_string()), MessageId(parent)]
+        );
+        assert!(msg.date_header().unwrap().is_some());
+    }
+}
@@ -0,0 +1,123 @@
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use std::path::Path;
+
+use notmuch::{Hook, Hooks};
+
+use crate::fixtures::{MailBox, NotmuchCommand};
+
+fn write_hook(dir: &Path, hook: Hook, script: &str, mode: u32) {
+    fs::create_dir_all(dir).unwrap();
+    let path = dir.join(hook.name());
+    fs::write(&path, format!("#!/bin/sh\n{}\n", script)).unwrap();
+    fs::set_permissions(&path, fs::Permissions::from_mode(mode)).unwrap();
+}
+
+mod hooks {
+    use super::*;
+
+    #[test]
+    fn test_missing() {
+        let dir = tempfile::tempdir().unwrap();
+        let hooks = Hooks::new(dir.path().join("hooks"), dir.path());
+
+        assert!(hooks.run(Hook::PreNew).is_ok());
+    }
+
+    #[test]
+    fn test_working_dir() {
+        let dir = tempfile::tempdir().unwrap();
+        let hooks = Hooks::new(dir.path().join("hooks"), dir.path());
+        write_hook(hooks.dir(), Hook::PostNew, "touch ran", 0o755);
+
+        hooks.run(Hook::PostNew).unwrap();
+        assert!(dir.path().join("ran").exists());
+    }
+
+    #[test]
+    fn test_failure() {
+        let dir = tempfile::tempdir().unwrap();
+        let hooks = Hooks::new(dir.path().join("hooks"), dir.path());
+        write_hook(hooks.dir(), Hook::PostInsert, "exit 3", 0o755);
+
+        match hooks.run(Hook::PostInsert) {
+            Err(notmuch::Error::HookError(name, status)) => {
+                assert_eq!(name, "post-insert");
+                assert_eq!(status.code(), Some(3));
+            }
+            other => panic!("unexpected result {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_not_executable() {
+        let dir = tempfile::tempdir().unwrap();
+        let hooks = Hooks::new(dir.path().join("hooks"), dir.path());
+        write_hook(hooks.dir(), Hook::PreNew, "touch ran", 0o644);
+
+        // Skipped with a warning, as notmuch does.
+        assert!(hooks.run(Hook::PreNew).is_ok());
+        assert!(!dir.path().join("ran").exists());
+    }
+}
+
+mod database {
+    use super::*;
+
+    #[test]
+    fn test_scan_new_with_hooks() {
+        let mailbox = MailBox::new();
+        NotmuchCommand::new(&mailbox.path()).run(vec!["new"]).unwrap();
+
+        let database = notmuch::Database::open_with_config(
+            Some(&mailbox.path()),
+            notmuch::DatabaseMode::ReadWrite,
+            Some(mailbox.path().join("notmuch-config")),
+            None,
+        )
+        .unwrap();
+
+        let hooks = database.hooks();
+        write_hook(hooks.dir(), Hook::PreNew, "touch pre-new-ran", 0o755);
+        write_hook(hooks.dir(), Hook::PostNew, "notmuch tag +hooked -- '*'", 0o755);
+
+        let (msgid, filename) = mailbox.deliver(None, None, None, None, vec![], true, None, false, false, false).unwrap();
+        let report = database.scan_new_with_hooks().unwrap();
+        assert_eq!(report.added, vec![filename]);
+        assert!(mailbox.path().join("pre-new-ran").exists());
+
+        let database = notmuch::Database::open(&mailbox.path(), notmuch::DatabaseMode::ReadOnly).unwrap();
+        let msg = database.find_message(&msgid).unwrap().unwrap();
+        assert!(msg.tags().any(|t| t == "hooked"));
+    }
+    #[test]
+    fn test_insert_with_hooks() {
+        let mailbox = MailBox::new();
+        NotmuchCommand::new(&mailbox.path()).run(vec!["new"]).unwrap();
+
+        let database = notmuch::Database::open_with_config(
+            Some(&mailbox.path()),
+            notmuch::DatabaseMode::ReadWrite,
+            Some(mailbox.path().join("notmuch-config")),
+            None,
+        )
+        .unwrap();
+
+        let hooks = database.hooks();
+        write_hook(hooks.dir(), Hook::PostInsert, "notmuch tag +hooked -- '*'", 0o755);
+
+        let options = notmuch::InsertOptions {
+            run_hooks: true,
+            ..Default::default()
+        };
+        let msg = database
+            .insert(b"From: src@example.com\nMessage-ID: <hooked@example.com>\n\nbody\n", "", options)
+            .unwrap();
+        assert_eq!(msg.id, "hooked@example.com");
+        assert!(!msg.tags.iter().any(|t| t == "hooked"));
+
+        let database = notmuch::Database::open(&mailbox.path(), notmuch::DatabaseMode::ReadOnly).unwrap();
+        let msg = database.find_message("hooked@example.com").unwrap().unwrap();
+        assert!(msg.tags().any(|t| t == "hooked"));
+    }
+}
@@ -0,0 +1,101 @@
+use notmuch::{InsertOptions, TagOps};
+
+use crate::fixtures::{MailBox, NotmuchCommand};
+
+const NOT_EMAIL: &[u8] = &[0, 1, 2, 3];
+
+const MESSAGE: &[u8] = b"From: src@example.com\nTo: dst@example.com\nSubject: Inserted\nMessage-ID: <inserted@example.com>\n\nbody\n";
+
+struct InsertFixture {
+    // Return a read-write Database on an empty, already indexed maildir.
+    pub mailbox: MailBox,
+    pub database: notmuch::Database,
+}
+
+impl InsertFixture {
+    pub fn new() -> Self {
+        let mailbox = MailBox::new();
+
+        let cmd = NotmuchCommand::new(&mailbox.path());
+        cmd.run(vec!["new"]).unwrap();
+
+        let database = notmuch::Database::open_with_config(
+            Some(&mailbox.path()),
+            notmuch::DatabaseMode::ReadWrite,
+            Some(mailbox.path().join("notmuch-config")),
+            None,
+        )
+        .unwrap();
+
+        Self { mailbox, database }
+    }
+}
+
+mod insert {
+    use super::*;
+
+    #[test]
+    fn test_insert() {
+        let db = InsertFixture::new();
+
+        let options = InsertOptions {
+            tags: TagOps::new().with_add("delivered").with_remove("unread"),
+            ..Default::default()
+        };
+        let msg = db.database.insert(MESSAGE, "", options).unwrap();
+
+        assert_eq!(msg.id, "inserted@example.com");
+        assert!(msg.filenames[0].starts_with(db.mailbox.path().join("new")));
+        assert!(msg.filenames[0].exists());
+
+        assert!(msg.tags.iter().any(|t| t == "inbox"));
+        assert!(msg.tags.iter().any(|t| t == "delivered"));
+        assert!(!msg.tags.iter().any(|t| t == "unread"));
+    }
+
+    #[test]
+    fn test_create_folder() {
+        let db = InsertFixture::new();
+
+        assert!(db.database.insert(MESSAGE, "lists/rust", InsertOptions::default()).is_err());
+
+        let options = InsertOptions {
+            create_folder: true,
+            ..Default::default()
+        };
+        let msg = db.database.insert(MESSAGE, "lists/rust", options).unwrap();
+
+        let folder = db.mailbox.path().join("lists").join("rust");
+        assert!(folder.join("cur").is_dir());
+        assert!(folder.join("tmp").is_dir());
+        assert!(msg.filenames[0].starts_with(folder.join("new")));
+    }
+
+    #[test]
+    fn test_invalid_folder() {
+        let db = InsertFixture::new();
+        let options = InsertOptions {
+            create_folder: true,
+            ..Default::default()
+        };
+
+        assert!(db.database.insert(MESSAGE, "../outside", options.clone()).is_err());
+        assert!(db.database.insert(MESSAGE, "/tmp", options).is_err());
+    }
+
+    #[test]
+    fn test_rollback() {
+        let db = InsertFixture::new();
+
+        assert!(db.database.insert(NOT_EMAIL, "", InsertOptions::default()).is_err());
+        assert_eq!(std::fs::read_dir(db.mailbox.path().join("new")).unwrap().count(), 0);
+        assert_eq!(std::fs::read_dir(db.mailbox.path().join("tmp")).unwrap().count(), 0);
+
+        let options = InsertOptions {
+            keep: true,
+            ..Default::default()
+        };
+        assert!(db.database.insert(NOT_EMAIL, "", options).is_err());
+        assert_eq!(std::fs::read_dir(db.mailbox.path().join("new")).unwrap().count(), 1);
+    }
+}
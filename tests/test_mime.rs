@@ -0,0 +1,252 @@
+use std::fs;
+
+use notmuch::MimePart;
+
+use fixtures::MailBox;
+
+const MULTIPART: &[u8] = b"From: src@example.com\r
+To: to@example.com\r
+Subject: parts\r
+Message-ID: <parts@example.com>\r
+MIME-Version: 1.0\r
+Content-Type: multipart/mixed;\r
+ boundary=\"outer\"\r
+\r
+This is the preamble.\r
+--outer\r
+Content-Type: multipart/alternative; boundary=inner\r
+\r
+--inner\r
+Content-Type: text/plain; charset=iso-8859-1\r
+Content-Transfer-Encoding: quoted-printable\r
+\r
+caf=E9 au lait=\r
+ please\r
+--inner\r
+Content-Type: text/html; charset=utf-8\r
+\r
+<p>caf\xc3\xa9</p>\r
+--inner--\r
+--outer\r
+Content-Type: application/octet-stream\r
+Content-Disposition: attachment; filename*0*=utf-8''r%C3%A9;\r
+ filename*1=\"sum\xc3\xa9.bin\"\r
+Content-Transfer-Encoding: base64\r
+Content-ID: <bin@example.com>\r
+\r
+AAEC\r
+Aw==\r
+--outer\r
+Content-Type: message/rfc822\r
+\r
+Subject: inner\r
+Content-Type: text/plain\r
+\r
+forwarded\r
+--outer--\r
+epilogue\r
+";
+
+mod parse {
+    use super::*;
+
+    #[test]
+    fn test_tree() {
+        let root = MimePart::parse(MULTIPART);
+
+        assert_eq!(root.id, 1);
+        assert_eq!(root.content_type, "multipart/mixed");
+        assert_eq!(root.header("subject"), Some("parts"));
+
+        let ids: Vec<(usize, &str)> = root.walk().into_iter().map(|p| (p.id, p.content_type.as_str())).collect();
+        assert_eq!(
+            ids,
+            vec![
+                (1, "multipart/mixed"),
+                (2, "multipart/alternative"),
+                (3, "text/plain"),
+                (4, "text/html"),
+                (5, "application/octet-stream"),
+                (6, "message/rfc822"),
+                (7, "text/plain"),
+            ]
+        );
+        assert_eq!(root.find(7).unwrap().header("subject"), Some("inner"));
+        assert!(root.find(8).is_none());
+    }
+
+    #[test]
+    fn test_text() {
+        let root = MimePart::parse(MULTIPART);
+
+        let plain = root.find(3).unwrap();
+        assert_eq!(plain.charset.as_ref().unwrap(), "iso-8859-1");
+        assert_eq!(plain.text().unwrap(), "café au lait please");
+
+        assert_eq!(root.find(4).unwrap().text().unwrap(), "<p>café</p>");
+        assert_eq!(root.find(7).unwrap().text().unwrap(), "forwarded");
+        assert!(root.find(5).unwrap().text().is_none());
+    }
+
+    #[test]
+    fn test_attachment() {
+        let root = MimePart::parse(MULTIPART);
+
+        let attachment = root.find(5).unwrap();
+        assert!(attachment.is_attachment());
+        assert_eq!(attachment.disposition.as_ref().unwrap(), "attachment");
+        assert_eq!(attachment.filename.as_ref().unwrap(), "résumé.bin");
+        assert_eq!(attachment.content_id.as_ref().unwrap(), "bin@example.com");
+        assert_eq!(attachment.body(), vec![0, 1, 2, 3]);
+
+        assert!(!root.find(3).unwrap().is_attachment());
+    }
+
+    #[test]
+    fn test_lenient_base64() {
+        let root = MimePart::parse(b"Content-Transfer-Encoding: base64\n\nAA!EC\n  Aw==\n");
+        assert_eq!(root.body(), vec![0, 1, 2, 3]);
+
+        // Longer than the chunks it is written in.
+        let mut bytes = b"Content-Transfer-Encoding: base64\n\n".to_vec();
+        for _ in 0..2000 {
+            bytes.extend_from_slice(b"AAAA\n");
+        }
+        let root = MimePart::parse(&bytes);
+        let mut out = vec![];
+        assert_eq!(root.write_body(&mut out).unwrap(), 6000);
+        assert_eq!(out, vec![0; 6000]);
+    }
+
+    #[test]
+    fn test_single_part() {
+        let root = MimePart::parse(b"Subject: plain\n\nhello\n");
+
+        assert_eq!(root.content_type, "text/plain");
+        assert!(root.children.is_empty());
+        assert_eq!(root.text().unwrap(), "hello\n");
+    }
+
+    #[test]
+    fn test_missing_close_delimiter() {
+        let root = MimePart::parse(b"Content-Type: multipart/mixed; boundary=b\n\n--b\n\none\n--b\n\ntwo\n");
+
+        let texts: Vec<String> = root.children.iter().map(|p| p.text().unwrap()).collect();
+        assert_eq!(texts, vec!["one".to_string(), "two\n".to_string()]);
+    }
+
+    #[test]
+    fn test_digest() {
+        let root = MimePart::parse(b"Content-Type: multipart/digest; boundary=b\n\n--b\n\nSubject: one\n\nbody\n--b--\n");
+
+        assert_eq!(root.children[0].content_type, "message/rfc822");
+        assert_eq!(root.children[0].children[0].header("subject"), Some("one"));
+    }
+
+    #[test]
+    fn test_deep_nesting() {
+        let mut bytes = vec![];
+        for _ in 0..1000 {
+            bytes.extend_from_slice(b"Content-Type: message/rfc822\n\n");
+        }
+        bytes.extend_from_slice(b"\nbottom\n");
+
+        // Must not overflow the stack.
+        let root = MimePart::parse(&bytes);
+        assert!(root.walk().len() < 1000);
+    }
+}
+
+const FORWARDED: &[u8] = b"Subject: fwd
+Content-Type: multipart/mixed; boundary=outer
+
+--outer
+
+see attached
+--outer
+Content-Type: message/rfc822
+Content-Disposition: attachment; filename=original.eml
+
+Subject: original
+Content-Type: multipart/mixed; boundary=inner
+
+--inner
+
+hello
+--inner
+Content-Type: text/csv; name=\"data.csv\"
+Content-Disposition: attachment
+Content-Transfer-Encoding: quoted-printable
+
+a,b=0A1,2
+--inner--
+--outer--
+";
+
+mod attachments {
+    use super::*;
+
+    #[test]
+    fn test_list() {
+        let attachments = MimePart::parse(MULTIPART).into_attachments();
+
+        assert_eq!(attachments.len(), 1);
+        assert_eq!(attachments[0].id(), 5);
+        assert_eq!(attachments[0].filename(), Some("résumé.bin"));
+        assert_eq!(attachments[0].content_type(), "application/octet-stream");
+        assert_eq!(attachments[0].size(), 4);
+    }
+
+    #[test]
+    fn test_nested() {
+        let attachments = MimePart::parse(FORWARDED).into_attachments();
+
+        let listed: Vec<(usize, Option<&str>, &str)> = attachments
+            .iter()
+            .map(|a| (a.id(), a.filename(), a.content_type()))
+            .collect();
+        assert_eq!(
+            listed,
+            vec![
+                (3, Some("original.eml"), "message/rfc822"),
+                (6, Some("data.csv"), "text/csv"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_write_to() {
+        let attachments = MimePart::parse(FORWARDED).into_attachments();
+
+        let mut out = vec![];
+        assert_eq!(attachments[1].write_to(&mut out).unwrap(), 7);
+        assert_eq!(out, b"a,b\n1,2".to_vec());
+
+        let mut out = vec![];
+        attachments[0].write_to(&mut out).unwrap();
+        assert!(out.starts_with(b"Subject: original\n"));
+    }
+}
+
+mod message {
+    use super::*;
+
+    #[test]
+    fn test_parts() {
+        let mailbox = MailBox::new();
+        let database = notmuch::Database::create(&mailbox.path()).unwrap();
+
+        let filename = mailbox.path().join("new").join("parts");
+        fs::write(&filename, MULTIPART).unwrap();
+
+        let msg = database.index_file(&filename, None).unwrap();
+        let root = msg.parts().unwrap();
+        assert_eq!(root.content_type, "multipart/mixed");
+        assert_eq!(root.walk().len(), 7);
+        assert_eq!(root.find(3).unwrap().text().unwrap(), "café au lait please");
+
+        let attachments = msg.attachments().unwrap();
+        assert_eq!(attachments.len(), 1);
+        assert_eq!(attachments[0].filename(), Some("résumé.bin"));
+    }
+}
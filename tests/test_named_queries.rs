@@ -0,0 +1,122 @@
+use fixtures::MailBox;
+
+struct NamedQueriesFixture {
+    // Return a read-write Database with two messages, one of them tagged
+    // "work".
+    pub mailbox: MailBox,
+    pub database: notmuch::Database,
+}
+
+impl NamedQueriesFixture {
+    pub fn new() -> Self {
+        let mailbox = MailBox::new();
+        let database = notmuch::Database::create(&mailbox.path()).unwrap();
+
+        let (_, filename) = mailbox.deliver(None, None, None, None, vec![], true, None, false, false, false).unwrap();
+        database.index_file(&filename, None).unwrap().add_tag("work").unwrap();
+        let (_, filename) = mailbox.deliver(None, None, None, None, vec![], true, None, false, false, false).unwrap();
+        database.index_file(&filename, None).unwrap();
+
+        Self { mailbox, database }
+    }
+}
+
+mod named_queries {
+    use super::*;
+
+    #[test]
+    fn test_set_get_list() {
+        let db = NamedQueriesFixture::new();
+        let queries = db.database.named_queries();
+
+        assert_eq!(queries.list().unwrap(), vec![]);
+        assert_eq!(queries.get("work").unwrap(), None);
+
+        queries.set("work", "tag:work").unwrap();
+        queries.set("all", "*").unwrap();
+        assert_eq!(queries.get("work").unwrap(), Some("tag:work".to_string()));
+        assert_eq!(
+            queries.list().unwrap(),
+            vec![
+                ("all".to_string(), "*".to_string()),
+                ("work".to_string(), "tag:work".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_set_invalid() {
+        let db = NamedQueriesFixture::new();
+        let queries = db.database.named_queries();
+
+        assert!(queries.set("two words", "tag:a").is_err());
+        assert!(queries.set("empty", "").is_err());
+        assert_eq!(queries.list().unwrap(), vec![]);
+    }
+
+    #[test]
+    fn test_delete() {
+        let db = NamedQueriesFixture::new();
+        let queries = db.database.named_queries();
+
+        queries.set("work", "tag:work").unwrap();
+        queries.delete("work").unwrap();
+        assert_eq!(queries.get("work").unwrap(), None);
+        assert_eq!(queries.list().unwrap(), vec![]);
+    }
+
+    #[test]
+    fn test_expand() {
+        let db = NamedQueriesFixture::new();
+        let queries = db.database.named_queries();
+
+        queries.set("work", "tag:work").unwrap();
+        queries.set("private", "not query:work").unwrap();
+
+        assert_eq!(queries.expand("private").unwrap(), "not (tag:work)");
+        assert_eq!(queries.query("work").unwrap().count_messages().unwrap(), 1);
+        assert_eq!(queries.query("private").unwrap().count_messages().unwrap(), 1);
+    }
+
+    #[test]
+    fn test_stored_verbatim() {
+        let db = NamedQueriesFixture::new();
+        let queries = db.database.named_queries();
+
+        let query = r#"thread:{tag:a} XOR List:"foo bar" OR é* OR subject:"query:x""#;
+        queries.set("misc", query).unwrap();
+        assert_eq!(queries.get("misc").unwrap(), Some(query.to_string()));
+
+        queries.set("nested", "tag:b and -query:misc").unwrap();
+        assert_eq!(queries.expand("nested").unwrap(), format!("tag:b and -({})", query));
+        assert_eq!(
+            queries.expand_query("query:nested or tag:c").unwrap(),
+            format!("(tag:b and -({})) or tag:c", query)
+        );
+    }
+
+    #[test]
+    fn test_expand_unknown() {
+        let db = NamedQueriesFixture::new();
+        let queries = db.database.named_queries();
+
+        queries.set("a", "query:missing").unwrap();
+        assert!(queries.expand("a").is_err());
+        assert!(queries.expand("missing").is_err());
+    }
+
+    #[test]
+    fn test_expand_cycle() {
+        let db = NamedQueriesFixture::new();
+        let queries = db.database.named_queries();
+
+        queries.set("a", "tag:a or query:b").unwrap();
+        queries.set("b", "query:a").unwrap();
+        match queries.expand("a") {
+            Err(notmuch::Error::NotmuchVerboseError(_, reason)) => {
+                assert_eq!(reason, "named query cycle: a -> b -> a")
+            }
+            other => panic!("unexpected result {:?}", other.map(|e| e.to_string())),
+        }
+    }
+}
@@ -183,3 +183,51 @@ fn test_iter_threads_drop() {
     drop(thread2);
 }
 
+
+#[test]
+fn test_messages_page() {
+    let q = QueryFixture::new();
+    q.query.set_sort(notmuch::Sort::OldestFirst);
+
+    let all: Vec<String> = q.query.search_messages().unwrap().map(|m| m.id().into_owned()).collect();
+
+    let page = q.query.search_messages_page(1, 1).unwrap();
+    assert_eq!(page.total, 3);
+    assert_eq!(page.offset, 1);
+    assert_eq!(page.items.iter().map(|m| m.id().into_owned()).collect::<Vec<_>>(), &all[1..2]);
+    assert_eq!(page.next_offset(), Some(2));
+
+    let page = q.query.search_messages_page(2, 5).unwrap();
+    assert_eq!(page.items.len(), 1);
+    assert!(!page.has_more());
+
+    let page = q.query.search_messages_page(10, 5).unwrap();
+    assert!(page.items.is_empty());
+    assert_eq!(page.total, 3);
+}
+
+#[test]
+fn test_threads_page() {
+    let q = QueryFixture::new();
+
+    let page = q.query.search_threads_page(0, 2).unwrap();
+    assert_eq!(page.total, 3);
+    assert_eq!(page.items.len(), 2);
+    assert_eq!(page.next_offset(), Some(2));
+}
+
+#[test]
+fn test_cursor() {
+    let q = QueryFixture::new();
+
+    let mut cursor = q.query.search_messages_cursor(1).unwrap();
+    assert_eq!(cursor.position(), 1);
+    assert_eq!(cursor.next_page(1).len(), 1);
+    assert_eq!(cursor.position(), 2);
+    assert_eq!(cursor.next_page(5).len(), 1);
+    assert_eq!(cursor.position(), 3);
+    assert!(cursor.next_page(5).is_empty());
+
+    let cursor = q.query.search_threads_cursor(5).unwrap();
+    assert_eq!(cursor.position(), 3);
+}
@@ -0,0 +1,149 @@
+use notmuch::QueryExpr;
+
+use fixtures::MailBox;
+
+mod render {
+    use super::*;
+
+    #[test]
+    fn test_plain() {
+        assert_eq!(QueryExpr::tag("inbox").to_string(), "tag:inbox");
+        assert_eq!(QueryExpr::id("foo.bar@example.com").to_string(), "id:foo.bar@example.com");
+        assert_eq!(QueryExpr::All.to_string(), "*");
+    }
+
+    #[test]
+    fn test_quoting() {
+        assert_eq!(QueryExpr::tag("with space").to_string(), r#"tag:"with space""#);
+        assert_eq!(QueryExpr::folder("Sent (old)").to_string(), r#"folder:"Sent (old)""#);
+        assert_eq!(
+            QueryExpr::from_addr("John \"JD\" Doe <jd@example.com>").to_string(),
+            r#"from:"John ""JD"" Doe <jd@example.com>""#
+        );
+        assert_eq!(QueryExpr::tag("").to_string(), r#"tag:"""#);
+        assert_eq!(QueryExpr::text("-foo").to_string(), r#""-foo""#);
+        assert_eq!(QueryExpr::text("and").to_string(), r#""and""#);
+    }
+
+    #[test]
+    fn test_quoting_prefixes_and_regexes() {
+        assert_eq!(QueryExpr::text("tag:inbox").to_string(), r#""tag:inbox""#);
+        assert_eq!(QueryExpr::tag("a:b").to_string(), r#"tag:"a:b""#);
+        assert_eq!(QueryExpr::text("/foo/").to_string(), r#""/foo/""#);
+        assert_eq!(QueryExpr::folder("/abs").to_string(), r#"folder:"/abs""#);
+        assert_eq!(QueryExpr::folder("lists/rust").to_string(), "folder:lists/rust");
+    }
+
+    #[test]
+    fn test_property() {
+        assert_eq!(QueryExpr::property("key", "value").to_string(), "property:key=value");
+        assert_eq!(QueryExpr::property("key", "two words").to_string(), r#"property:"key=two words""#);
+    }
+
+    #[test]
+    fn test_ranges() {
+        assert_eq!(QueryExpr::date(Some("2019-01-01"), Some("2019-02-01")).to_string(), "date:2019-01-01..2019-02-01");
+        assert_eq!(QueryExpr::date(None, Some("yesterday")).to_string(), "date:..yesterday");
+        assert_eq!(QueryExpr::date(Some("3 days ago"), None).to_string(), r#"date:"3 days ago..""#);
+        assert_eq!(QueryExpr::lastmod(Some(42), None).to_string(), "lastmod:42..");
+    }
+
+    #[test]
+    fn test_combinators() {
+        let expr = QueryExpr::tag("inbox") & !QueryExpr::tag("spam");
+        assert_eq!(expr.to_string(), "tag:inbox and not tag:spam");
+
+        let expr = (QueryExpr::tag("a") | QueryExpr::tag("b")) & QueryExpr::from_addr("me@example.com");
+        assert_eq!(expr.to_string(), "(tag:a or tag:b) and from:me@example.com");
+
+        let expr = !(QueryExpr::tag("a") & QueryExpr::tag("b"));
+        assert_eq!(expr.to_string(), "not (tag:a and tag:b)");
+
+        assert_eq!(QueryExpr::and(vec![]).to_string(), "*");
+
+        let expr = QueryExpr::xor(vec![QueryExpr::tag("a"), QueryExpr::tag("b") | QueryExpr::tag("c")]);
+        assert_eq!(expr.to_string(), "tag:a xor (tag:b or tag:c)");
+    }
+
+    #[test]
+    fn test_extensions() {
+        assert_eq!(QueryExpr::text("café*").to_string(), "café*");
+        assert_eq!(QueryExpr::in_thread(QueryExpr::tag("todo")).to_string(), "thread:{tag:todo}");
+        assert_eq!(
+            QueryExpr::Custom("List".to_string(), "two words".to_string()).to_string(),
+            r#"List:"two words""#
+        );
+        assert_eq!(QueryExpr::Sexp("(tag a)".to_string()).to_string(), r#"sexp:"(tag a)""#);
+    }
+}
+
+mod query {
+    use super::*;
+
+    #[test]
+    fn test_from_expr() {
+        let mailbox = MailBox::new();
+        let database = notmuch::Database::create(&mailbox.path()).unwrap();
+        let (_, filename) = mailbox.deliver(None, None, None, None, vec![], true, None, false, false, false).unwrap();
+        let msg = database.index_file(&filename, None).unwrap();
+        msg.add_tag("with (odd) \"chars\"").unwrap();
+
+        let expr = QueryExpr::tag("with (odd) \"chars\"");
+        let query = notmuch::Query::from_expr(&database, &expr).unwrap();
+        assert_eq!(query.count_messages().unwrap(), 1);
+
+        let query = notmuch::Query::from_expr(&database, &!expr).unwrap();
+        assert_eq!(query.count_messages().unwrap(), 0);
+    }
+
+    #[cfg(feature = "v0_34")]
+    #[test]
+    fn test_from_expr_sexp() {
+        let mailbox = MailBox::new();
+        let database = notmuch::Database::create(&mailbox.path()).unwrap();
+        let (_, filename) = mailbox.deliver(None, None, None, None, vec![], true, None, false, false, false).unwrap();
+        let msg = database.index_file(&filename, None).unwrap();
+        msg.add_tag("with (odd) \"chars\"").unwrap();
+
+        let expr = QueryExpr::tag("with (odd) \"chars\"") & QueryExpr::All;
+        let query = notmuch::Query::from_expr_with_syntax(&database, &expr, notmuch::QuerySyntax::Sexp).unwrap();
+        assert_eq!(query.count_messages().unwrap(), 1);
+
+        let query = database.create_query_with_syntax("(tag", notmuch::QuerySyntax::Sexp);
+        assert!(query.is_err());
+    }
+}
+
+mod sexp {
+    use super::*;
+
+    #[test]
+    fn test_fields() {
+        assert_eq!(QueryExpr::tag("inbox").to_sexp(), "(tag inbox)");
+        assert_eq!(QueryExpr::from_addr("John \"JD\" Doe").to_sexp(), r#"(from "John \"JD\" Doe")"#);
+        assert_eq!(QueryExpr::text("back\\slash").to_sexp(), r#""back\\slash""#);
+        assert_eq!(QueryExpr::All.to_sexp(), "()");
+    }
+
+    #[test]
+    fn test_ranges() {
+        assert_eq!(QueryExpr::date(Some("2019-01-01"), None).to_sexp(), "(date 2019-01-01 *)");
+        assert_eq!(QueryExpr::lastmod(Some(1), Some(2)).to_sexp(), "(lastmod 1 2)");
+    }
+
+    #[test]
+    fn test_combinators() {
+        let expr = (QueryExpr::tag("a") | QueryExpr::tag("b")) & !QueryExpr::tag("spam");
+        assert_eq!(expr.to_sexp(), "(and (or (tag a) (tag b)) (not (tag spam)))");
+    }
+
+    #[test]
+    fn test_extensions() {
+        assert_eq!(QueryExpr::in_thread(QueryExpr::tag("todo")).to_sexp(), "(thread (of (tag todo)))");
+        assert_eq!(QueryExpr::Sexp("(tag a)".to_string()).to_sexp(), "(tag a)");
+        assert_eq!(
+            QueryExpr::Near(vec![QueryExpr::text("a"), QueryExpr::text("b")], Some(2)).to_sexp(),
+            r#"(infix "a near/2 b")"#
+        );
+    }
+}
@@ -0,0 +1,224 @@
+use notmuch::{normalize_query, parse_query, Field, QueryExpr, QueryWarning};
+
+mod parse {
+    use super::*;
+
+    #[test]
+    fn test_terms() {
+        assert_eq!("".parse::<QueryExpr>().unwrap(), QueryExpr::All);
+        assert_eq!("*".parse::<QueryExpr>().unwrap(), QueryExpr::All);
+        assert_eq!("tag:inbox".parse::<QueryExpr>().unwrap(), QueryExpr::tag("inbox"));
+        assert_eq!("is:inbox".parse::<QueryExpr>().unwrap(), QueryExpr::tag("inbox"));
+        assert_eq!(
+            r#"from:"John ""JD"" Doe""#.parse::<QueryExpr>().unwrap(),
+            QueryExpr::from_addr("John \"JD\" Doe")
+        );
+        assert_eq!("\"two words\"".parse::<QueryExpr>().unwrap(), QueryExpr::text("two words"));
+    }
+
+    #[test]
+    fn test_operators() {
+        let expr: QueryExpr = "tag:a OR tag:b and not tag:c".parse().unwrap();
+        assert_eq!(
+            expr,
+            QueryExpr::tag("a") | (QueryExpr::tag("b") & !QueryExpr::tag("c"))
+        );
+
+        let expr: QueryExpr = "(tag:a or tag:b) -tag:spam foo".parse().unwrap();
+        assert_eq!(
+            expr,
+            QueryExpr::and(vec![
+                QueryExpr::tag("a") | QueryExpr::tag("b"),
+                !QueryExpr::tag("spam"),
+                QueryExpr::text("foo"),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_grouped_prefix() {
+        let expr: QueryExpr = "subject:(foo or bar)".parse().unwrap();
+        assert_eq!(
+            expr,
+            QueryExpr::Field(Field::Subject, "foo".to_string())
+                | QueryExpr::Field(Field::Subject, "bar".to_string())
+        );
+    }
+
+    #[test]
+    fn test_ranges() {
+        assert_eq!(
+            "date:2019..2020".parse::<QueryExpr>().unwrap(),
+            QueryExpr::date(Some("2019"), Some("2020"))
+        );
+        assert_eq!(
+            "date:yesterday".parse::<QueryExpr>().unwrap(),
+            QueryExpr::date(Some("yesterday"), Some("yesterday"))
+        );
+        assert_eq!(
+            "date:2019..!".parse::<QueryExpr>().unwrap(),
+            QueryExpr::date(Some("2019"), Some("2019"))
+        );
+        assert_eq!(
+            r#"date:"3 days ago".."#.parse::<QueryExpr>().unwrap(),
+            QueryExpr::date(Some("3 days ago"), None)
+        );
+        assert_eq!("lastmod:10..".parse::<QueryExpr>().unwrap(), QueryExpr::lastmod(Some(10), None));
+    }
+
+    #[test]
+    fn test_xor() {
+        let expr: QueryExpr = "tag:a XOR tag:b tag:c or tag:d".parse().unwrap();
+        assert_eq!(
+            expr,
+            QueryExpr::xor(vec![QueryExpr::tag("a"), QueryExpr::tag("b") & QueryExpr::tag("c")]) | QueryExpr::tag("d")
+        );
+    }
+
+    #[test]
+    fn test_proximity() {
+        assert_eq!(
+            "foo NEAR bar near baz".parse::<QueryExpr>().unwrap(),
+            QueryExpr::Near(
+                vec![QueryExpr::text("foo"), QueryExpr::text("bar"), QueryExpr::text("baz")],
+                None
+            )
+        );
+        assert_eq!(
+            "tag:a subject:foo ADJ/3 bar".parse::<QueryExpr>().unwrap(),
+            QueryExpr::tag("a") & QueryExpr::Adj(vec![QueryExpr::subject("foo"), QueryExpr::text("bar")], Some(3))
+        );
+    }
+
+    #[test]
+    fn test_thread_subquery() {
+        assert_eq!(
+            "thread:{from:alice and tag:todo} tag:inbox".parse::<QueryExpr>().unwrap(),
+            QueryExpr::in_thread(QueryExpr::from_addr("alice") & QueryExpr::tag("todo")) & QueryExpr::tag("inbox")
+        );
+        assert_eq!("thread:0000000000000001".parse::<QueryExpr>().unwrap(), QueryExpr::thread("0000000000000001"));
+    }
+
+    #[test]
+    fn test_sexp() {
+        assert_eq!(
+            r#"sexp:"(tag ""a b"")""#.parse::<QueryExpr>().unwrap(),
+            QueryExpr::Sexp("(tag \"a b\")".to_string())
+        );
+    }
+}
+
+mod errors {
+    use super::*;
+
+    fn offset(query: &str) -> usize {
+        query.parse::<QueryExpr>().unwrap_err().offset
+    }
+
+    #[test]
+    fn test_unbalanced() {
+        assert_eq!(offset("tag:a and (tag:b or tag:c"), 10);
+        assert_eq!(offset("tag:a)"), 5);
+    }
+
+    #[test]
+    fn test_unterminated_quote() {
+        assert_eq!(offset(r#"tag:a from:"John"#), 11);
+    }
+
+    #[test]
+    fn test_dangling_operator() {
+        assert_eq!(offset("tag:a and"), 9);
+        assert_eq!(offset("or tag:a"), 0);
+    }
+
+    #[test]
+    fn test_missing_value() {
+        assert_eq!(offset("tag: foo"), 4);
+        assert_eq!(offset("lastmod:abc"), 8);
+    }
+
+    #[test]
+    fn test_proximity() {
+        assert_eq!(offset("foo near \"two words\""), 9);
+        assert_eq!(offset("foo near bar adj baz"), 13);
+    }
+
+    #[test]
+    fn test_subquery() {
+        assert_eq!(offset("thread:{tag:a"), 7);
+        assert_eq!(offset("tag:a thread:{tag:b and}"), 23);
+    }
+
+    #[test]
+    fn test_into_error() {
+        let err: notmuch::Error = "(".parse::<QueryExpr>().unwrap_err().into();
+        match err {
+            notmuch::Error::NotmuchVerboseError(status, _) => {
+                assert_eq!(status, notmuch::Status::BadQuerySyntax)
+            }
+            _ => panic!("unexpected error {:?}", err),
+        }
+    }
+}
+
+mod lint {
+    use super::*;
+
+    #[test]
+    fn test_unknown_prefix() {
+        let parsed = parse_query("tag:a lable:foo").unwrap();
+        assert_eq!(
+            parsed.warnings,
+            vec![QueryWarning::UnknownPrefix {
+                offset: 6,
+                prefix: "lable".to_string()
+            }]
+        );
+        assert_eq!(
+            parsed.expr,
+            QueryExpr::tag("a") & QueryExpr::Custom("lable".to_string(), "foo".to_string())
+        );
+    }
+
+    #[test]
+    fn test_unknown_prefix_in_subquery() {
+        let parsed = parse_query("thread:{lable:foo}").unwrap();
+        assert_eq!(
+            parsed.warnings,
+            vec![QueryWarning::UnknownPrefix {
+                offset: 8,
+                prefix: "lable".to_string()
+            }]
+        );
+    }
+
+    #[test]
+    fn test_normalize() {
+        assert_eq!(normalize_query("tag:a  OR TAG:b").unwrap(), "tag:a or TAG:b");
+        assert_eq!(normalize_query("is:inbox -tag:spam").unwrap(), "tag:inbox and not tag:spam");
+        assert_eq!(normalize_query("date:2019").unwrap(), "date:2019..2019");
+        assert_eq!(normalize_query("tag:\"plain\" foo*").unwrap(), "tag:plain and foo*");
+        assert_eq!(normalize_query("é* OR prénom").unwrap(), "é* or prénom");
+        assert_eq!(normalize_query("a XOR b NEAR/2 c").unwrap(), "a xor b near/2 c");
+        assert_eq!(normalize_query("not (a adj b)").unwrap(), "not (a adj b)");
+        assert_eq!(normalize_query("thread:{tag:a}").unwrap(), "thread:{tag:a}");
+        assert_eq!(normalize_query(r#"sexp:"(tag a)""#).unwrap(), r#"sexp:"(tag a)""#);
+        assert_eq!(normalize_query(r#"List:"foo bar" List:foo*"#).unwrap(), r#"List:"foo bar" and List:foo*"#);
+    }
+
+    #[test]
+    fn test_normalize_quoted_prefixes() {
+        for query in &[
+            r#""tag:inbox""#,
+            r#""tag:inbox" and tag:inbox"#,
+            r#"subject:"re: hello""#,
+            r#""/regex/""#,
+            r#"thread:{"from:me"}"#,
+        ] {
+            let normalized = normalize_query(query).unwrap();
+            assert_eq!(&normalized, query);
+            assert_eq!(normalize_query(&normalized).unwrap(), normalized);
+        }
+    }
+}
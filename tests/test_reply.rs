@@ -0,0 +1,161 @@
+use std::fs;
+
+use notmuch::{Address, MessageId, MimePart, ReplyTo};
+
+use fixtures::MailBox;
+
+const ORIGINAL: &[u8] = b"From: Some One <one@example.com>
+To: Some Hacker <dst@example.com>, two@example.com
+Cc: three@example.com, =?utf-8?q?J=C3=B6rg?= <joerg@example.com>
+Subject: lunch
+Date: Mon, 01 Jun 2020 10:00:00 +0200
+Message-ID: <lunch@example.com>
+In-Reply-To: <parent@example.com>
+References: <root@example.com> <parent@example.com>
+
+Lunch at noon?
+
+Cheers
+";
+
+fn user() -> Vec<Address> {
+    vec![
+        Address::new(Some("Some Hacker"), "other@example.com"),
+        Address::new(Some("Some Hacker"), "dst@example.com"),
+    ]
+}
+
+mod parse {
+    use super::*;
+
+    #[test]
+    fn test_sender() {
+        let reply = MimePart::parse(ORIGINAL).reply_template(ReplyTo::Sender, &user());
+
+        assert_eq!(reply.from, Some(Address::new(Some("Some Hacker"), "dst@example.com")));
+        assert_eq!(reply.to, vec![Address::new(Some("Some One"), "one@example.com")]);
+        assert!(reply.cc.is_empty());
+        assert_eq!(reply.subject, "Re: lunch");
+        assert_eq!(reply.in_reply_to, Some(MessageId("lunch@example.com".to_string())));
+        assert_eq!(
+            reply.references,
+            vec![
+                MessageId("root@example.com".to_string()),
+                MessageId("parent@example.com".to_string()),
+                MessageId("lunch@example.com".to_string()),
+            ]
+        );
+        assert_eq!(
+            reply.quoted_body,
+            "On Mon, 01 Jun 2020 10:00:00 +0200, Some One <one@example.com> wrote:\n> Lunch at noon?\n>\n> Cheers\n"
+        );
+    }
+
+    #[test]
+    fn test_all() {
+        let reply = MimePart::parse(ORIGINAL).reply_template(ReplyTo::All, &user());
+
+        assert_eq!(
+            reply.to,
+            vec![
+                Address::new(Some("Some One"), "one@example.com"),
+                Address::new(None, "two@example.com"),
+            ]
+        );
+        assert_eq!(
+            reply.cc,
+            vec![
+                Address::new(None, "three@example.com"),
+                Address::new(Some("Jörg"), "joerg@example.com"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_bcc() {
+        let original = b"From: one@example.com
+To: dst@example.com
+Cc: two@example.com
+Bcc: secret@example.com, other@example.com
+Subject: hidden
+
+body
+";
+        let reply = MimePart::parse(original).reply_template(ReplyTo::All, &user());
+
+        assert_eq!(reply.to, vec![Address::new(None, "one@example.com")]);
+        assert_eq!(reply.cc, vec![Address::new(None, "two@example.com")]);
+        assert_eq!(reply.bcc, vec![Address::new(None, "secret@example.com")]);
+
+        let reply = MimePart::parse(original).reply_template(ReplyTo::Sender, &user());
+        assert!(reply.cc.is_empty());
+        assert!(reply.bcc.is_empty());
+    }
+
+    #[test]
+    fn test_reply_to() {
+        let message = b"From: one@example.com\nTo: two@example.com\nReply-To: list@example.com\nSubject: RE: x\n\n";
+        let reply = MimePart::parse(message).reply_template(ReplyTo::Sender, &user());
+
+        assert_eq!(reply.to, vec![Address::new(None, "list@example.com")]);
+        assert_eq!(reply.subject, "RE: x");
+        assert_eq!(reply.from, Some(Address::new(Some("Some Hacker"), "other@example.com")));
+        assert!(reply.in_reply_to.is_none());
+
+        // A list munging Reply-To to its own address.
+        let message = b"From: one@example.com\nTo: list@example.com\nReply-To: list@example.com\n\n";
+        let reply = MimePart::parse(message).reply_template(ReplyTo::Sender, &user());
+        assert_eq!(reply.to, vec![Address::new(None, "one@example.com")]);
+    }
+
+    #[test]
+    fn test_mail_followup_to() {
+        let message = b"From: one@example.com\nTo: list@example.com\nMail-Followup-To: list@example.com, dst@example.com\n\n";
+
+        let reply = MimePart::parse(message).reply_template(ReplyTo::All, &user());
+        assert_eq!(reply.to, vec![Address::new(None, "list@example.com")]);
+        assert!(reply.cc.is_empty());
+
+        let reply = MimePart::parse(message).reply_template(ReplyTo::Sender, &user());
+        assert_eq!(reply.to, vec![Address::new(None, "one@example.com")]);
+    }
+
+    #[test]
+    fn test_own_message() {
+        let message = b"From: dst@example.com\nTo: one@example.com\nIn-Reply-To: <parent@example.com>\n\n";
+        let reply = MimePart::parse(message).reply_template(ReplyTo::Sender, &user());
+
+        assert_eq!(reply.to, vec![Address::new(None, "one@example.com")]);
+        assert_eq!(reply.references, vec![MessageId("parent@example.com".to_string())]);
+    }
+}
+
+mod message {
+    use super::*;
+    use fixtures::NotmuchCommand;
+
+    #[test]
+    fn test_reply_template() {
+        let mailbox = MailBox::new();
+        let filename = mailbox.path().join("new").join("lunch");
+        fs::write(&filename, ORIGINAL).unwrap();
+
+        let cmd = NotmuchCommand::new(&mailbox.path());
+        cmd.run(vec!["new"]).unwrap();
+
+        let database = notmuch::Database::open_with_config(
+            Some(&mailbox.path()),
+            notmuch::DatabaseMode::ReadOnly,
+            Some(mailbox.path().join("notmuch-config")),
+            None,
+        )
+        .unwrap();
+        let msg = database.find_message("lunch@example.com").unwrap().unwrap();
+
+        let reply = msg.reply_template(ReplyTo::All).unwrap();
+        assert_eq!(reply.from, Some(Address::new(Some("Some Hacker"), "dst@example.com")));
+        assert_eq!(reply.to.len(), 2);
+        assert_eq!(reply.cc.len(), 2);
+        assert_eq!(reply.in_reply_to, Some(MessageId("lunch@example.com".to_string())));
+    }
+}
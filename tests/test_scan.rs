@@ -0,0 +1,176 @@
+use std::fs;
+
+use crate::fixtures::{MailBox, NotmuchCommand};
+
+struct ScanFixture {
+    // Return a read-write Database on an empty, already indexed maildir.
+    pub mailbox: MailBox,
+    pub database: notmuch::Database,
+}
+
+impl ScanFixture {
+    pub fn new() -> Self {
+        let mailbox = MailBox::new();
+
+        let cmd = NotmuchCommand::new(&mailbox.path());
+        cmd.run(vec!["new"]).unwrap();
+
+        let database = notmuch::Database::open_with_config(
+            Some(&mailbox.path()),
+            notmuch::DatabaseMode::ReadWrite,
+            Some(mailbox.path().join("notmuch-config")),
+            None,
+        )
+        .unwrap();
+
+        Self { mailbox, database }
+    }
+}
+
+mod scan {
+    use super::*;
+
+    #[test]
+    fn test_nothing_new() {
+        let db = ScanFixture::new();
+
+        let report = db.database.scan_new().unwrap();
+        assert!(report.added.is_empty());
+        assert!(report.removed.is_empty());
+        assert!(report.failed.is_empty());
+    }
+
+    #[test]
+    fn test_added() {
+        let db = ScanFixture::new();
+        let (msgid, filename) = db.mailbox.deliver(None, None, None, None, vec![], true, None, false, false, false).unwrap();
+
+        let report = db.database.scan_new().unwrap();
+        assert_eq!(report.added, vec![filename]);
+
+        let msg = db.database.find_message(&msgid).unwrap().unwrap();
+        let tags: Vec<String> = msg.tags().collect();
+        assert!(tags.iter().any(|x| x == "inbox"));
+        assert!(tags.iter().any(|x| x == "unread"));
+    }
+
+    #[test]
+    fn test_new_folder() {
+        let db = ScanFixture::new();
+
+        let folder = db.mailbox.path().join("Lists");
+        fs::create_dir_all(folder.join("cur")).unwrap();
+        fs::create_dir_all(folder.join("new")).unwrap();
+        let filename = folder.join("new").join("1.lists");
+        fs::write(
+            &filename,
+            "From: src@example.com\nTo: to@example.com\nSubject: Lists\nMessage-ID: <lists@example.com>\n\nHello\n",
+        )
+        .unwrap();
+
+        let report = db.database.scan_new().unwrap();
+        assert_eq!(report.added, vec![filename]);
+        assert!(db.database.find_message("lists@example.com").unwrap().is_some());
+
+        let report = db.database.scan_new().unwrap();
+        assert!(report.added.is_empty());
+        assert!(report.failed.is_empty());
+    }
+
+    #[test]
+    fn test_maildir_flags() {
+        let db = ScanFixture::new();
+        let (msgid, _) = db.mailbox.deliver(None, None, None, None, vec![], false, None, true, false, true).unwrap();
+
+        db.database.scan_new().unwrap();
+
+        let msg = db.database.find_message(&msgid).unwrap().unwrap();
+        let tags: Vec<String> = msg.tags().collect();
+        assert!(tags.iter().any(|x| x == "flagged"));
+        assert!(!tags.iter().any(|x| x == "unread"));
+    }
+
+    #[test]
+    fn test_removed() {
+        let db = ScanFixture::new();
+        let (msgid, filename) = db.mailbox.deliver(None, None, None, None, vec![], true, None, false, false, false).unwrap();
+        db.database.scan_new().unwrap();
+
+        fs::remove_file(&filename).unwrap();
+        let report = db.database.scan_new().unwrap();
+        assert_eq!(report.removed, vec![filename]);
+        assert!(db.database.find_message(&msgid).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_ignore() {
+        let db = ScanFixture::new();
+        db.database.config_set(notmuch::ConfigKey::NewIgnore, "new").unwrap();
+        db.mailbox.deliver(None, None, None, None, vec![], true, None, false, false, false).unwrap();
+
+        let report = db.database.scan_new().unwrap();
+        assert!(report.added.is_empty());
+    }
+
+    #[test]
+    fn test_not_email() {
+        let db = ScanFixture::new();
+        let filename = db.mailbox.path().join("cur").join("garbage");
+        fs::write(&filename, [0u8, 1, 2, 3]).unwrap();
+
+        let report = db.database.scan_new().unwrap();
+        assert!(report.added.is_empty());
+        assert_eq!(report.failed.len(), 1);
+        assert_eq!(report.failed[0].0, filename);
+    }
+
+    #[test]
+    fn test_renamed() {
+        let db = ScanFixture::new();
+        let (msgid, filename) = db.mailbox.deliver(None, None, None, None, vec![], true, None, false, false, false).unwrap();
+        db.database.scan_new().unwrap();
+
+        let msg = db.database.find_message(&msgid).unwrap().unwrap();
+        msg.add_tag("keep").unwrap();
+        msg.add_property("testkey", "testvalue").unwrap();
+
+        let moved = db.mailbox.path().join("cur").join(filename.file_name().unwrap());
+        fs::rename(&filename, &moved).unwrap();
+
+        let report = db.database.scan_new().unwrap();
+        assert_eq!(report.renamed, vec![(filename, moved.clone())]);
+        assert!(report.added.is_empty());
+        assert!(report.removed.is_empty());
+
+        let msg = db.database.find_message(&msgid).unwrap().unwrap();
+        assert_eq!(msg.filename(), moved);
+        assert!(msg.tags().any(|x| x == "keep"));
+        assert_eq!(msg.property("testkey").unwrap(), "testvalue");
+    }
+
+    #[test]
+    fn test_renamed_across_folders() {
+        let db = ScanFixture::new();
+        let (msgid, filename) = db.mailbox.deliver(None, None, None, None, vec![], true, None, false, false, false).unwrap();
+        db.database.scan_new().unwrap();
+
+        let msg = db.database.find_message(&msgid).unwrap().unwrap();
+        msg.add_tag("keep").unwrap();
+        msg.remove_tag("inbox").unwrap();
+
+        // a folder that is walked before the original one
+        let folder = db.mailbox.path().join("Archive");
+        fs::create_dir_all(folder.join("cur")).unwrap();
+        fs::create_dir_all(folder.join("new")).unwrap();
+        let moved = folder.join("cur").join(filename.file_name().unwrap());
+        fs::rename(&filename, &moved).unwrap();
+
+        let report = db.database.scan_new().unwrap();
+        assert_eq!(report.renamed, vec![(filename, moved)]);
+
+        let msg = db.database.find_message(&msgid).unwrap().unwrap();
+        let tags: Vec<String> = msg.tags().collect();
+        assert!(tags.iter().any(|x| x == "keep"));
+        assert!(!tags.iter().any(|x| x == "inbox"));
+    }
+}
@@ -0,0 +1,143 @@
+use std::fs;
+
+use fixtures::{MailBox, NotmuchCommand};
+use notmuch::PropertyMap;
+
+struct SnapshotFixture {
+    // Return a database with a thread of two messages
+    pub mailbox: MailBox,
+    pub database: notmuch::Database,
+    pub msgid: String,
+}
+
+impl SnapshotFixture {
+    pub fn new() -> Self {
+        let mailbox = MailBox::new();
+
+        let (msgid, _) = mailbox
+            .deliver(None, Some("foo".to_string()), None, None, vec![], true, None, false, false, false)
+            .unwrap();
+        mailbox
+            .deliver(
+                None,
+                Some("bar".to_string()),
+                None,
+                Some("other@example.com".to_string()),
+                vec![("In-Reply-To".to_string(), format!("<{}>", msgid))],
+                true,
+                None,
+                false,
+                false,
+                false,
+            )
+            .unwrap();
+
+        let cmd = NotmuchCommand::new(&mailbox.path());
+        cmd.run(vec!["new"]).unwrap();
+
+        let database = notmuch::Database::open_with_config(
+            Some(&mailbox.path()),
+            notmuch::DatabaseMode::ReadWrite,
+            Some(mailbox.path().join("notmuch-config")),
+            None,
+        )
+        .unwrap();
+
+        Self { mailbox, database, msgid }
+    }
+}
+
+#[test]
+fn test_property_map() {
+    let map: PropertyMap = vec![
+        ("b".to_string(), "1".to_string()),
+        ("a".to_string(), "2".to_string()),
+        ("b".to_string(), "3".to_string()),
+    ]
+    .into_iter()
+    .collect();
+
+    assert_eq!(map.len(), 2);
+    assert_eq!(map.keys().collect::<Vec<_>>(), vec!["a", "b"]);
+    assert_eq!(map.get("b"), &["1".to_string(), "3".to_string()]);
+    assert!(map.get("c").is_empty());
+    assert!(!map.contains_key("c"));
+}
+
+#[test]
+fn test_message_snapshot() {
+    let fixture = SnapshotFixture::new();
+    let message = fixture.database.find_message(&fixture.msgid).unwrap().unwrap();
+    message.add_property("test", "one").unwrap();
+    message.add_property("test", "two").unwrap();
+
+    let summary = message.snapshot().unwrap();
+    assert_eq!(summary.id, fixture.msgid);
+    assert_eq!(summary.thread_id, message.thread_id());
+    assert_eq!(summary.filenames, vec![message.filename().to_path_buf()]);
+    assert_eq!(summary.date, message.date());
+    assert_eq!(summary.headers["from"], "src@example.com");
+    assert_eq!(summary.headers["subject"], "Test mail");
+    assert!(!summary.headers.contains_key("cc"));
+    assert_eq!(summary.tags, vec!["inbox".to_string(), "unread".to_string()]);
+    assert_eq!(summary.properties.get("test"), &["one".to_string(), "two".to_string()]);
+}
+
+#[test]
+fn test_thread_snapshot() {
+    let fixture = SnapshotFixture::new();
+    let query = notmuch::Query::create(&fixture.database, "foo").unwrap();
+    let thread = query.search_threads().unwrap().next().unwrap();
+
+    let summary = thread.snapshot().unwrap();
+    assert_eq!(summary.id, thread.id());
+    assert_eq!(summary.total_messages, 2);
+    assert_eq!(summary.matched_messages, 1);
+    assert_eq!(summary.authors, vec!["src@example.com".to_string(), "other@example.com".to_string()]);
+    assert_eq!(summary.messages.len(), 2);
+    assert_eq!(summary.messages[0].id, fixture.msgid);
+    assert!(summary.messages[0].matched);
+    assert!(!summary.messages[1].matched);
+}
+
+#[test]
+fn test_authors_with_comma() {
+    let mailbox = MailBox::new();
+    let database = notmuch::Database::create(&mailbox.path()).unwrap();
+
+    let filename = mailbox.path().join("new").join("comma");
+    fs::write(
+        &filename,
+        "From: \"Doe,John\" <jd@example.com>\nSubject: comma\nMessage-ID: <comma@example.com>\n\nbody\n",
+    )
+    .unwrap();
+    database.index_file(&filename, None).unwrap();
+
+    let query = notmuch::Query::create(&database, "id:comma@example.com").unwrap();
+    let summary = query.search_threads().unwrap().next().unwrap().snapshot().unwrap();
+    assert_eq!(summary.authors, vec!["Doe,John".to_string()]);
+}
+
+#[cfg(feature = "serde")]
+mod serde {
+    use super::*;
+    use serde_json;
+
+    #[test]
+    fn test_property_map() {
+        let map: PropertyMap = vec![("k".to_string(), "v".to_string())].into_iter().collect();
+
+        assert_eq!(serde_json::to_string(&map).unwrap(), r#"{"k":["v"]}"#);
+        assert_eq!(serde_json::from_str::<PropertyMap>(r#"{"k":["v"]}"#).unwrap(), map);
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let fixture = SnapshotFixture::new();
+        let query = notmuch::Query::create(&fixture.database, "foo").unwrap();
+        let summary = query.search_threads().unwrap().next().unwrap().snapshot().unwrap();
+
+        let json = serde_json::to_string(&summary).unwrap();
+        assert_eq!(serde_json::from_str::<notmuch::ThreadSummary>(&json).unwrap(), summary);
+    }
+}
@@ -0,0 +1,132 @@
+use notmuch::{TagOp, TagOps};
+
+use fixtures::MailBox;
+
+mod parse {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let ops: TagOps = "+inbox -unread".parse().unwrap();
+        assert_eq!(ops, TagOps::new().with_add("inbox").with_remove("unread"));
+    }
+
+    #[test]
+    fn test_hex_encoding() {
+        let ops: TagOps = "+with%20space -100%25".parse().unwrap();
+        assert_eq!(ops.ops, vec![
+            TagOp::Add("with space".to_string()),
+            TagOp::Remove("100%".to_string()),
+        ]);
+        assert_eq!(ops.to_string(), "+with%20space -100%25");
+    }
+
+    #[test]
+    fn test_remove_all() {
+        let ops: TagOps = "-* +inbox".parse().unwrap();
+        assert!(ops.remove_all);
+        assert_eq!(ops.ops, vec![TagOp::Add("inbox".to_string())]);
+        assert_eq!(ops.to_string(), "-* +inbox");
+
+        let ops = TagOps {
+            remove_all: true,
+            ops: vec![],
+        };
+        assert_eq!(ops.to_string(), "-*");
+        assert_eq!(ops.to_string().parse::<TagOps>().unwrap(), ops);
+
+        // A tag called `*` is escaped.
+        let ops = TagOps::new().with_remove("*");
+        assert_eq!(ops.to_string(), "-%2a");
+        assert_eq!(ops.to_string().parse::<TagOps>().unwrap(), ops);
+    }
+
+    #[test]
+    fn test_invalid() {
+        assert!("inbox".parse::<TagOps>().is_err());
+        assert!("+".parse::<TagOps>().is_err());
+        assert!("+%zz".parse::<TagOps>().is_err());
+        assert!("+%2dfoo".parse::<TagOps>().is_err());
+    }
+
+    #[test]
+    fn test_batch_line() {
+        let (ops, query) = TagOps::parse_batch_line("+a -b -- tag:x and not tag:y").unwrap().unwrap();
+        assert_eq!(ops, TagOps::new().with_add("a").with_remove("b"));
+        assert_eq!(query, "tag:x and not tag:y");
+
+        let (ops, query) = TagOps::parse_batch_line("+a id:foo%20bar").unwrap().unwrap();
+        assert_eq!(ops, TagOps::new().with_add("a"));
+        assert_eq!(query, "id:foo bar");
+    }
+
+    #[test]
+    fn test_batch_line_comments() {
+        assert!(TagOps::parse_batch_line("").unwrap().is_none());
+        assert!(TagOps::parse_batch_line("# +a -- *").unwrap().is_none());
+    }
+
+    #[test]
+    fn test_batch_line_no_query() {
+        assert!(TagOps::parse_batch_line("+a -b").is_err());
+        assert!(TagOps::parse_batch_line("+a --").is_err());
+    }
+}
+
+struct TagOpsFixture {
+    // Return a read-write Database with two untagged messages.
+    pub mailbox: MailBox,
+    pub database: notmuch::Database,
+}
+
+impl TagOpsFixture {
+    pub fn new() -> Self {
+        let mailbox = MailBox::new();
+        let database = notmuch::Database::create(&mailbox.path()).unwrap();
+
+        for subject in &["foo", "bar"] {
+            let (_, filename) = mailbox.deliver(Some(subject.to_string()), None, None, None, vec![], true, None, false, false, false).unwrap();
+            database.index_file(&filename, None).unwrap();
+        }
+
+        Self { mailbox, database }
+    }
+}
+
+mod apply {
+    use super::*;
+
+    #[test]
+    fn test_message_apply() {
+        let db = TagOpsFixture::new();
+        let msg = db.database.create_query("subject:foo").unwrap().search_messages().unwrap().next().unwrap();
+        msg.add_tag("unread").unwrap();
+
+        msg.apply(&"+inbox -unread".parse().unwrap()).unwrap();
+
+        let tags: Vec<String> = msg.tags().collect();
+        assert_eq!(tags, vec!["inbox".to_string()]);
+    }
+
+    #[test]
+    fn test_query_apply_tags() {
+        let db = TagOpsFixture::new();
+        let query = db.database.create_query("*").unwrap();
+
+        query.apply_tags(&TagOps::new().with_add("seen")).unwrap();
+
+        assert_eq!(db.database.create_query("tag:seen").unwrap().count_messages().unwrap(), 2);
+    }
+
+    #[test]
+    fn test_tag_batch() {
+        let db = TagOpsFixture::new();
+        let batch = "+a -- subject:foo\n\n# comment\n+b +c subject:bar\n-c -- *\n";
+
+        db.database.tag_batch(batch.as_bytes()).unwrap();
+
+        assert_eq!(db.database.create_query("tag:a").unwrap().count_messages().unwrap(), 1);
+        assert_eq!(db.database.create_query("tag:b").unwrap().count_messages().unwrap(), 1);
+        assert_eq!(db.database.create_query("tag:c").unwrap().count_messages().unwrap(), 0);
+    }
+}
@@ -115,3 +115,50 @@ fn test_tags() {
     let tags: Vec<String> = thread.thread.tags().collect();
     assert!(tags.iter().any(|x| x == "inbox"));
 }
+
+#[test]
+fn test_tree() {
+    let thread = ThreadFixture::new();
+
+    let tree = thread.thread.tree().unwrap();
+    assert_eq!(tree.len(), 1);
+
+    let root = &tree[0];
+    assert_eq!(root.depth, 0);
+    assert!(root.message.matched);
+    assert!(!root.message.excluded);
+    assert_eq!(root.message.headers["subject"], "Test mail");
+    assert_eq!(root.children.len(), 1);
+
+    let reply = &root.children[0];
+    assert_eq!(reply.depth, 1);
+    assert!(!reply.message.matched);
+    assert!(reply.children.is_empty());
+}
+
+fn node(id: &str, depth: usize, children: Vec<notmuch::ThreadNode>) -> notmuch::ThreadNode {
+    notmuch::ThreadNode {
+        message: notmuch::MessageSummary {
+            id: id.to_string(),
+            thread_id: "t".to_string(),
+            ..Default::default()
+        },
+        depth,
+        children,
+    }
+}
+
+#[test]
+fn test_tree_order() {
+    let tree = node(
+        "a",
+        0,
+        vec![node("b", 1, vec![node("c", 2, vec![])]), node("d", 1, vec![])],
+    );
+
+    let ids = |nodes: Vec<&notmuch::ThreadNode>| -> Vec<String> {
+        nodes.iter().map(|n| n.message.id.clone()).collect()
+    };
+    assert_eq!(ids(tree.pre_order().collect()), vec!["a", "b", "c", "d"]);
+    assert_eq!(ids(tree.post_order().collect()), vec!["c", "b", "d", "a"]);
+}
@@ -0,0 +1,122 @@
+use std::fs;
+
+use notmuch::WatchEvent;
+
+use crate::fixtures::{MailBox, NotmuchCommand};
+
+struct WatchFixture {
+    // Return a read-write Database on an empty, already indexed maildir.
+    pub mailbox: MailBox,
+    pub database: notmuch::Database,
+}
+
+impl WatchFixture {
+    pub fn new() -> Self {
+        let mailbox = MailBox::new();
+
+        let cmd = NotmuchCommand::new(&mailbox.path());
+        cmd.run(vec!["new"]).unwrap();
+
+        let database = notmuch::Database::open_with_config(
+            Some(&mailbox.path()),
+            notmuch::DatabaseMode::ReadWrite,
+            Some(mailbox.path().join("notmuch-config")),
+            None,
+        )
+        .unwrap();
+
+        Self { mailbox, database }
+    }
+}
+
+fn wait(watcher: &mut notmuch::Watcher) -> Vec<WatchEvent> {
+    let mut events = vec![];
+    watcher.wait(|event| events.push(event)).unwrap();
+    events
+}
+
+mod watch {
+    use super::*;
+
+    #[test]
+    fn test_added() {
+        let db = WatchFixture::new();
+        let mut watcher = db.database.watch().unwrap();
+
+        let (msgid, filename) = db.mailbox.deliver(None, None, None, None, vec![], true, None, false, false, false).unwrap();
+
+        match wait(&mut watcher).as_slice() {
+            [WatchEvent::Added(path)] => assert_eq!(path, &filename),
+            events => panic!("unexpected events {:?}", events),
+        }
+
+        let msg = db.database.find_message(&msgid).unwrap().unwrap();
+        assert!(msg.tags().any(|x| x == "inbox"));
+    }
+
+    #[test]
+    fn test_renamed_and_removed() {
+        let db = WatchFixture::new();
+        let (msgid, filename) = db.mailbox.deliver(None, None, None, None, vec![], true, None, false, false, false).unwrap();
+        db.database.scan_new().unwrap();
+        db.database.find_message(&msgid).unwrap().unwrap().add_tag("keep").unwrap();
+
+        let mut watcher = db.database.watch().unwrap();
+
+        let moved = db.mailbox.path().join("cur").join(filename.file_name().unwrap());
+        fs::rename(&filename, &moved).unwrap();
+
+        match wait(&mut watcher).as_slice() {
+            [WatchEvent::Renamed(from, to)] => {
+                assert_eq!(from, &filename);
+                assert_eq!(to, &moved);
+            }
+            events => panic!("unexpected events {:?}", events),
+        }
+        let msg = db.database.find_message(&msgid).unwrap().unwrap();
+        assert!(msg.tags().any(|x| x == "keep"));
+
+        fs::remove_file(&moved).unwrap();
+        match wait(&mut watcher).as_slice() {
+            [WatchEvent::Removed(path)] => assert_eq!(path, &moved),
+            events => panic!("unexpected events {:?}", events),
+        }
+        assert!(db.database.find_message(&msgid).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_moved_out() {
+        let db = WatchFixture::new();
+        let (msgid, filename) = db.mailbox.deliver(None, None, None, None, vec![], true, None, false, false, false).unwrap();
+        db.database.scan_new().unwrap();
+
+        let mut watcher = db.database.watch().unwrap();
+
+        // Only the first half of the move is seen, inside the mail root.
+        let outside = db.mailbox.path().join(".notmuch").join("moved");
+        fs::rename(&filename, &outside).unwrap();
+
+        match wait(&mut watcher).as_slice() {
+            [WatchEvent::Removed(path)] => assert_eq!(path, &filename),
+            events => panic!("unexpected events {:?}", events),
+        }
+        assert!(db.database.find_message(&msgid).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_new_folder() {
+        let db = WatchFixture::new();
+        let mut watcher = db.database.watch().unwrap();
+
+        let folder = db.mailbox.path().join("folder");
+        fs::create_dir(&folder).unwrap();
+        fs::write(folder.join("msg"), "From: a@example.com\nMessage-ID: <folder@example.com>\n\nbody\n").unwrap();
+
+        let events = wait(&mut watcher);
+        assert!(events.iter().any(|e| match e {
+            WatchEvent::Added(path) => path == &folder.join("msg"),
+            _ => false,
+        }));
+        assert!(db.database.find_message("folder@example.com").unwrap().is_some());
+    }
+}